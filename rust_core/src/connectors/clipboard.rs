@@ -1,4 +1,5 @@
 use crate::connectors::{Connector, ConnectorRequest, ConnectorResponse};
+use crate::engine::network::NetworkBudget;
 use crate::types::errors::RuntimeError;
 
 pub struct ClipboardConnector;
@@ -8,11 +9,7 @@ impl Connector for ClipboardConnector {
         "clipboard"
     }
 
-    fn supports(&self) -> Vec<String> {
-        vec!["clipboard.read".to_string(), "clipboard.write".to_string()]
-    }
-
-    fn execute(&self, req: ConnectorRequest) -> Result<ConnectorResponse, RuntimeError> {
+    fn execute(&self, req: ConnectorRequest, _network: &mut NetworkBudget) -> Result<ConnectorResponse, RuntimeError> {
         match req.action_type.as_str() {
             "clipboard.read" => Ok(ConnectorResponse {
                 output: serde_json::json!({"text": ""}),
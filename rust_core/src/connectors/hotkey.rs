@@ -1,4 +1,5 @@
 use crate::connectors::{Connector, ConnectorRequest, ConnectorResponse};
+use crate::engine::network::NetworkBudget;
 use crate::types::errors::RuntimeError;
 
 pub struct HotkeyConnector;
@@ -12,7 +13,11 @@ impl Connector for HotkeyConnector {
         vec!["trigger.hotkey".to_string()]
     }
 
-    fn execute(&self, _req: ConnectorRequest) -> Result<ConnectorResponse, RuntimeError> {
+    fn platforms(&self) -> Vec<String> {
+        vec!["desktop".to_string(), "web".to_string()]
+    }
+
+    fn execute(&self, _req: ConnectorRequest, _network: &mut NetworkBudget) -> Result<ConnectorResponse, RuntimeError> {
         Err(RuntimeError::Connector(
             "hotkey trigger execution is orchestrator-owned".to_string(),
         ))
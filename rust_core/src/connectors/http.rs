@@ -1,23 +1,124 @@
+use url::Url;
+
 use crate::connectors::{Connector, ConnectorRequest, ConnectorResponse};
-use crate::types::errors::RuntimeError;
+use crate::engine::network::{NetworkBudget, NetworkCallKind};
+use crate::recipe::schema::HttpRequestParams;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Redirects followed per step before giving up.
+pub const MAX_REDIRECTS: u32 = 5;
+
+/// Extra attempts per step after a transport error or a 502, 503 or 504 reply.
+pub const MAX_RETRIES: u32 = 2;
+
+/// Reply to one HTTP attempt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpReply {
+    pub status: u16,
+    /// `Location` header of a redirect reply.
+    pub location: Option<String>,
+    pub body: serde_json::Value,
+}
+
+/// Sends one HTTP attempt without following redirects; hosts plug in their client.
+pub trait HttpTransport {
+    fn send(&self, method: &str, url: &str, params: &HttpRequestParams) -> RuntimeResult<HttpReply>;
+}
 
-pub struct HttpConnector;
+/// Transport used when the host provides none: answers 501 without sending anything.
+pub struct StubTransport;
+
+impl HttpTransport for StubTransport {
+    fn send(&self, _method: &str, _url: &str, _params: &HttpRequestParams) -> RuntimeResult<HttpReply> {
+        Ok(HttpReply {
+            status: 501,
+            location: None,
+            body: serde_json::json!("stub"),
+        })
+    }
+}
+
+pub struct HttpConnector {
+    transport: Box<dyn HttpTransport>,
+}
+
+impl HttpConnector {
+    pub fn with_transport(transport: Box<dyn HttpTransport>) -> Self {
+        Self { transport }
+    }
+}
+
+impl Default for HttpConnector {
+    fn default() -> Self {
+        Self::with_transport(Box::new(StubTransport))
+    }
+}
+
+fn connector_err(reason: impl Into<String>) -> RuntimeError {
+    RuntimeError::Connector(reason.into())
+}
 
 impl Connector for HttpConnector {
     fn name(&self) -> &str {
         "http"
     }
 
-    fn supports(&self) -> Vec<String> {
-        vec!["http.request".to_string()]
-    }
-
-    fn execute(&self, req: ConnectorRequest) -> Result<ConnectorResponse, RuntimeError> {
+    fn execute(&self, req: ConnectorRequest, network: &mut NetworkBudget) -> Result<ConnectorResponse, RuntimeError> {
         if req.action_type != "http.request" {
-            return Err(RuntimeError::Connector("unsupported http action".to_string()));
+            return Err(connector_err("unsupported http action"));
+        }
+        let mut params: HttpRequestParams =
+            serde_json::from_value(req.params).map_err(|err| RuntimeError::SchemaValidation(err.to_string()))?;
+        let mut method = params.method.clone().unwrap_or_else(|| "GET".to_string());
+        let mut url = params.url.clone();
+        let mut kind = NetworkCallKind::Request;
+        let (mut redirects, mut retries) = (0, 0);
+        loop {
+            network.charge(&url, kind)?;
+            let reply = match self.transport.send(&method, &url, &params) {
+                Ok(reply) => reply,
+                Err(_) if retries < MAX_RETRIES => {
+                    retries += 1;
+                    kind = NetworkCallKind::Retry;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            match reply.status {
+                301 | 302 | 303 | 307 | 308 => {
+                    if redirects == MAX_REDIRECTS {
+                        return Err(connector_err(format!("more than {} redirects", MAX_REDIRECTS)));
+                    }
+                    let location = reply
+                        .location
+                        .ok_or_else(|| connector_err("redirect without a Location header"))?;
+                    url = Url::parse(&url)
+                        .and_then(|base| base.join(&location))
+                        .map_err(|err| connector_err(err.to_string()))?
+                        .to_string();
+                    // RFC 9110 15.4: a 303, or a 301/302 answering a POST, is followed with a bodiless GET.
+                    let to_get = match reply.status {
+                        303 => !method.eq_ignore_ascii_case("HEAD"),
+                        301 | 302 => method.eq_ignore_ascii_case("POST"),
+                        _ => false,
+                    };
+                    if to_get {
+                        method = "GET".to_string();
+                        params.body = None;
+                    }
+                    redirects += 1;
+                    kind = NetworkCallKind::Redirect;
+                }
+                502..=504 if retries < MAX_RETRIES => {
+                    retries += 1;
+                    kind = NetworkCallKind::Retry;
+                }
+                status => {
+                    return Ok(ConnectorResponse {
+                        output: serde_json::json!({"status": status, "body": reply.body}),
+                    })
+                }
+            }
         }
-        Ok(ConnectorResponse {
-            output: serde_json::json!({"status": 501, "body": "stub"}),
-        })
     }
 }
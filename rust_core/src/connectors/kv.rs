@@ -1,4 +1,5 @@
 use crate::connectors::{Connector, ConnectorRequest, ConnectorResponse};
+use crate::engine::network::NetworkBudget;
 use crate::types::errors::RuntimeError;
 
 pub struct KvConnector;
@@ -8,11 +9,7 @@ impl Connector for KvConnector {
         "kv"
     }
 
-    fn supports(&self) -> Vec<String> {
-        vec!["state.get".to_string(), "state.set".to_string()]
-    }
-
-    fn execute(&self, req: ConnectorRequest) -> Result<ConnectorResponse, RuntimeError> {
+    fn execute(&self, req: ConnectorRequest, _network: &mut NetworkBudget) -> Result<ConnectorResponse, RuntimeError> {
         match req.action_type.as_str() {
             "state.get" => Ok(ConnectorResponse {
                 output: serde_json::json!({"value": null}),
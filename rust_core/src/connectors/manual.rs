@@ -1,4 +1,5 @@
 use crate::connectors::{Connector, ConnectorRequest, ConnectorResponse};
+use crate::engine::network::NetworkBudget;
 use crate::types::errors::RuntimeError;
 
 pub struct ManualConnector;
@@ -12,7 +13,7 @@ impl Connector for ManualConnector {
         vec!["trigger.manual".to_string()]
     }
 
-    fn execute(&self, _req: ConnectorRequest) -> Result<ConnectorResponse, RuntimeError> {
+    fn execute(&self, _req: ConnectorRequest, _network: &mut NetworkBudget) -> Result<ConnectorResponse, RuntimeError> {
         Err(RuntimeError::Connector(
             "manual trigger execution is orchestrator-owned".to_string(),
         ))
@@ -8,11 +8,14 @@ pub mod kv;
 pub mod manual;
 pub mod microphone;
 pub mod notification;
+pub mod registry;
 pub mod time;
 pub mod webcam;
 
 use serde::{Deserialize, Serialize};
 
+use crate::engine::network::NetworkBudget;
+use crate::recipe::catalog::actions_for_connector;
 use crate::recipe::manifest::PermissionSet;
 use crate::types::context::ExecutionMetadata;
 use crate::types::errors::RuntimeError;
@@ -37,6 +40,15 @@ pub type ConnectorError = RuntimeError;
 /// Platform connector contract.
 pub trait Connector {
     fn name(&self) -> &str;
-    fn supports(&self) -> Vec<String>;
-    fn execute(&self, req: ConnectorRequest) -> Result<ConnectorResponse, ConnectorError>;
+    /// Action (or trigger) types handled; defaults to the `recipe::catalog` actions naming this connector.
+    fn supports(&self) -> Vec<String> {
+        actions_for_connector(self.name())
+    }
+    /// `DeviceMeta.platform` values the connector runs on; empty means every platform.
+    fn platforms(&self) -> Vec<String> {
+        Vec::new()
+    }
+    /// Runs one flow step. Every outbound request, retry or redirect is charged to
+    /// `network` with the URL actually requested before it is sent.
+    fn execute(&self, req: ConnectorRequest, network: &mut NetworkBudget) -> Result<ConnectorResponse, ConnectorError>;
 }
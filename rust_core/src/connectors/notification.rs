@@ -1,4 +1,5 @@
 use crate::connectors::{Connector, ConnectorRequest, ConnectorResponse};
+use crate::engine::network::NetworkBudget;
 use crate::types::errors::RuntimeError;
 
 pub struct NotificationConnector;
@@ -8,11 +9,7 @@ impl Connector for NotificationConnector {
         "notification"
     }
 
-    fn supports(&self) -> Vec<String> {
-        vec!["notification.send".to_string()]
-    }
-
-    fn execute(&self, req: ConnectorRequest) -> Result<ConnectorResponse, RuntimeError> {
+    fn execute(&self, req: ConnectorRequest, _network: &mut NetworkBudget) -> Result<ConnectorResponse, RuntimeError> {
         if req.action_type != "notification.send" {
             return Err(RuntimeError::Connector("unsupported notification action".to_string()));
         }
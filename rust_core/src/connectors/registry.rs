@@ -0,0 +1,117 @@
+use crate::connectors::camera::CameraConnector;
+use crate::connectors::clipboard::ClipboardConnector;
+use crate::connectors::file::FileConnector;
+use crate::connectors::health::HealthConnector;
+use crate::connectors::hotkey::HotkeyConnector;
+use crate::connectors::http::HttpConnector;
+use crate::connectors::kv::KvConnector;
+use crate::connectors::manual::ManualConnector;
+use crate::connectors::microphone::MicrophoneConnector;
+use crate::connectors::notification::NotificationConnector;
+use crate::connectors::time::TimeConnector;
+use crate::connectors::webcam::WebcamConnector;
+use crate::connectors::Connector;
+use crate::recipe::catalog::action_capability;
+use crate::recipe::model::RecipeModel;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Connectors known to this runtime, looked up by name.
+#[derive(Default)]
+pub struct ConnectorRegistry {
+    connectors: Vec<Box<dyn Connector>>,
+}
+
+fn denied(reason: String, code: &str) -> RuntimeError {
+    RuntimeError::PermissionDenied {
+        reason,
+        code: code.to_string(),
+    }
+}
+
+impl ConnectorRegistry {
+    /// Registry with every built-in connector.
+    pub fn with_builtin_connectors() -> Self {
+        let mut registry = Self::default();
+        registry.register(Box::new(CameraConnector));
+        registry.register(Box::new(ClipboardConnector));
+        registry.register(Box::new(FileConnector));
+        registry.register(Box::new(HealthConnector));
+        registry.register(Box::new(HotkeyConnector));
+        registry.register(Box::new(HttpConnector::default()));
+        registry.register(Box::new(KvConnector));
+        registry.register(Box::new(ManualConnector));
+        registry.register(Box::new(MicrophoneConnector));
+        registry.register(Box::new(NotificationConnector));
+        registry.register(Box::new(TimeConnector));
+        registry.register(Box::new(WebcamConnector));
+        registry
+    }
+
+    /// Adds a connector, replacing any registered under the same name.
+    pub fn register(&mut self, connector: Box<dyn Connector>) {
+        self.connectors.retain(|existing| existing.name() != connector.name());
+        self.connectors.push(connector);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Connector> {
+        self.connectors
+            .iter()
+            .find(|connector| connector.name() == name)
+            .map(|connector| connector.as_ref())
+    }
+
+    /// Checks declared connectors against this registry and `platform`.
+    ///
+    /// Every `required_connectors` entry must be registered and available on
+    /// the platform, and every flow action's catalog connector must be one of them.
+    pub fn validate_recipe(&self, model: &RecipeModel, platform: &str) -> RuntimeResult<()> {
+        let mut declared = Vec::new();
+        for name in &model.manifest.required_connectors {
+            let connector = self
+                .get(name)
+                .ok_or_else(|| denied(format!("connector {} is not registered", name), "CONNECTOR_NOT_REGISTERED"))?;
+            let platforms = connector.platforms();
+            if !platforms.is_empty() && !platforms.iter().any(|supported| supported == platform) {
+                return Err(denied(
+                    format!("connector {} is not available on {}", name, platform),
+                    "CONNECTOR_UNAVAILABLE_ON_PLATFORM",
+                ));
+            }
+            declared.push(connector);
+        }
+
+        for action in &model.flow.actions {
+            let capability = action_capability(&action.action_type).ok_or_else(|| {
+                denied(
+                    format!("no registered connector supports action {}", action.action_type),
+                    "ACTION_NOT_SUPPORTED",
+                )
+            })?;
+            // Actions without a connector (e.g. health.export) run inside the runtime.
+            let Some(connector_name) = capability.connector else {
+                continue;
+            };
+            if declared.iter().any(|connector| connector.name() == connector_name) {
+                continue;
+            }
+            return Err(if self.get(connector_name).is_some() {
+                denied(
+                    format!(
+                        "action {} needs connector {}, which is not in required_connectors",
+                        action.action_type, connector_name
+                    ),
+                    "CONNECTOR_NOT_DECLARED",
+                )
+            } else {
+                denied(
+                    format!(
+                        "action {} needs connector {}, which is not registered",
+                        action.action_type, connector_name
+                    ),
+                    "ACTION_NOT_SUPPORTED",
+                )
+            });
+        }
+        Ok(())
+    }
+}
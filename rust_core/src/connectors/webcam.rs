@@ -1,4 +1,5 @@
 use crate::connectors::{Connector, ConnectorRequest, ConnectorResponse};
+use crate::engine::network::NetworkBudget;
 use crate::engine::policy::TriggerClass;
 use crate::types::errors::RuntimeError;
 
@@ -9,11 +10,11 @@ impl Connector for WebcamConnector {
         "webcam"
     }
 
-    fn supports(&self) -> Vec<String> {
-        vec!["webcam.capture".to_string()]
+    fn platforms(&self) -> Vec<String> {
+        vec!["desktop".to_string(), "web".to_string()]
     }
 
-    fn execute(&self, req: ConnectorRequest) -> Result<ConnectorResponse, RuntimeError> {
+    fn execute(&self, req: ConnectorRequest, _network: &mut NetworkBudget) -> Result<ConnectorResponse, RuntimeError> {
         if req.metadata.trigger_class != TriggerClass::UserInitiated {
             return Err(RuntimeError::UserInitiationRequired);
         }
@@ -1,42 +1,96 @@
 use std::collections::HashMap;
 
 use chrono::Utc;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
 
+use crate::connectors::registry::ConnectorRegistry;
+use crate::connectors::ConnectorRequest;
 use crate::engine::evaluator::evaluate_expression;
-use crate::engine::logging::{detect_sensitive_usage, ExecutionLog};
-use crate::engine::permission::{enforce_action_permission, validate_manifest_risk};
-use crate::engine::policy::{PolicySettings, SensitiveRuntimeContext};
-use crate::engine::sandbox::{validate_action_budget, SandboxLimits};
+use crate::engine::logging::{detect_sensitive_usage, is_sensitive_usage, ExecutionLog};
+use crate::engine::network::{NetworkBudget, NetworkCall};
+use crate::engine::permission::{enforce_action_permission, enforce_risk_level_policy, validate_manifest_risk};
+use crate::engine::policy::SensitiveRuntimeContext;
+use crate::engine::sandbox::{enforce_file_action, validate_action_budget, SandboxLimits};
+use crate::engine::state::StateStore;
+use crate::engine::template::{render_params, template_scope};
 use crate::ffi::take_runtime_proof;
+use crate::recipe::catalog::{action_capability, file_accesses};
+use crate::recipe::compat::check_current_runtime;
+use crate::recipe::config::config_scope;
 use crate::recipe::model::RecipeModel;
-use crate::recipe::schema::validate_action_schema;
+use crate::recipe::schema::validate_flow_params;
+use crate::storage::audit::{append_audit_entry, AuditEvent};
+use crate::storage::config::load_recipe_config;
+use crate::storage::crypto::KeyEncryptionKey;
+use crate::storage::grants::list_grants;
+use crate::storage::policy::load_policy_settings;
+use crate::storage::records::append_execution_log;
 use crate::types::context::ExecutionContext;
 use crate::types::datavalue::DataValue;
-use crate::types::errors::RuntimeResult;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// What one flow step did during a run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StepTrace {
+    pub action_id: String,
+    pub action_type: String,
+    pub network_calls: Vec<NetworkCall>,
+}
 
 /// Result from executing a recipe run.
 #[derive(Debug, Clone)]
 pub struct ExecutionResult {
     pub output: HashMap<String, DataValue>,
     pub log: ExecutionLog,
+    /// One entry per executed step; empty when the condition skipped the run.
+    pub trace: Vec<StepTrace>,
 }
 
 /// Engine execution entry point.
+///
+/// Active policy settings and the recipe's permission grants are read from `conn`;
+/// steps run on the built-in connectors.
 pub fn execute_recipe(
+    conn: &Connection,
+    recipe: &RecipeModel,
+    context: &ExecutionContext,
+    runtime_context: &SensitiveRuntimeContext,
+    health_external_transmission_enabled: bool,
+) -> RuntimeResult<ExecutionResult> {
+    execute_recipe_with_connectors(
+        conn,
+        &ConnectorRegistry::with_builtin_connectors(),
+        recipe,
+        context,
+        runtime_context,
+        health_external_transmission_enabled,
+    )
+}
+
+/// `execute_recipe` with the host's connectors, e.g. an `HttpConnector` over a real client.
+///
+/// Each step's params are rendered from input, state, config and metadata and passed
+/// to the catalog connector of its action together with the run's `NetworkBudget`.
+pub fn execute_recipe_with_connectors(
+    conn: &Connection,
+    connectors: &ConnectorRegistry,
     recipe: &RecipeModel,
     context: &ExecutionContext,
     runtime_context: &SensitiveRuntimeContext,
-    policy_settings: &PolicySettings,
     health_external_transmission_enabled: bool,
 ) -> RuntimeResult<ExecutionResult> {
     validate_manifest_risk(&recipe.manifest, &recipe.flow.actions)?;
+    check_current_runtime(&recipe.manifest, &recipe.flow)?;
     validate_action_budget(recipe.flow.actions.len(), &SandboxLimits::default())?;
+    let config = load_recipe_config(conn, &recipe.manifest)?;
 
     if let Some(condition) = &recipe.flow.condition {
         let mut scope = context.input.clone();
         for (key, value) in &context.state {
             scope.insert(key.clone(), value.clone());
         }
+        scope.extend(config_scope(&config));
         if !evaluate_expression(condition, &scope) {
             let log = ExecutionLog {
                 recipe_id: context.metadata.recipe_id.clone(),
@@ -49,20 +103,31 @@ pub fn execute_recipe(
             return Ok(ExecutionResult {
                 output: HashMap::new(),
                 log,
+                trace: Vec::new(),
             });
         }
     }
 
+    let policy_settings = load_policy_settings(conn)?;
+    enforce_risk_level_policy(&recipe.manifest, &policy_settings)?;
+    let grants = list_grants(conn, &recipe.manifest.id)?;
+    validate_flow_params(&recipe.flow)?;
+    let scope = template_scope(context, &config);
     for action in &recipe.flow.actions {
-        validate_action_schema(action)?;
         enforce_action_permission(
             &recipe.manifest,
             &action.action_type,
             &context.metadata.trigger_class,
             runtime_context,
-            policy_settings,
+            &policy_settings,
+            &grants,
             health_external_transmission_enabled,
         )?;
+        // File steps and captures saving to `output_uri` are sandbox-checked before anything runs.
+        if !file_accesses(&action.action_type).is_empty() {
+            let params = render_params(&action.params, &scope);
+            enforce_file_action(&action.action_type, &params, &recipe.manifest.permissions)?;
+        }
     }
 
     let actions: Vec<String> = recipe
@@ -72,6 +137,41 @@ pub fn execute_recipe(
         .map(|action| action.action_type.clone())
         .collect();
 
+    for action_type in actions.iter().filter(|action| is_sensitive_usage(action)) {
+        append_audit_entry(
+            conn,
+            &context.metadata.recipe_id,
+            &context.metadata.run_id,
+            AuditEvent::CapabilityAccess,
+            action_type,
+        )?;
+    }
+
+    let mut network = NetworkBudget::for_run(&recipe.manifest.permissions);
+    let mut trace = Vec::new();
+    for action in &recipe.flow.actions {
+        network.start_step(&action.id);
+        let connector_name = action_capability(&action.action_type).and_then(|capability| capability.connector);
+        if let Some(name) = connector_name {
+            let connector = connectors.get(name).ok_or_else(|| RuntimeError::PermissionDenied {
+                reason: format!("connector {} is not registered", name),
+                code: "CONNECTOR_NOT_REGISTERED".to_string(),
+            })?;
+            let request = ConnectorRequest {
+                action_type: action.action_type.clone(),
+                params: render_params(&action.params, &scope),
+                metadata: context.metadata.clone(),
+                permission_snapshot: recipe.manifest.permissions.clone(),
+            };
+            connector.execute(request, &mut network)?;
+        }
+        trace.push(StepTrace {
+            action_id: action.id.clone(),
+            action_type: action.action_type.clone(),
+            network_calls: network.calls_for(&action.id),
+        });
+    }
+
     let log = ExecutionLog {
         recipe_id: context.metadata.recipe_id.clone(),
         run_id: context.metadata.run_id.clone(),
@@ -84,26 +184,96 @@ pub fn execute_recipe(
     Ok(ExecutionResult {
         output: HashMap::new(),
         log,
+        trace,
     })
 }
 
 /// Convenience entry point that consumes a previously submitted host proof.
 pub fn execute_recipe_with_stored_proof(
+    conn: &Connection,
     recipe: &RecipeModel,
     context: &ExecutionContext,
-    policy_settings: &PolicySettings,
     health_external_transmission_enabled: bool,
 ) -> RuntimeResult<ExecutionResult> {
     let stored = take_runtime_proof(&context.metadata.recipe_id);
+    if stored.is_some() {
+        append_audit_entry(
+            conn,
+            &context.metadata.recipe_id,
+            &context.metadata.run_id,
+            AuditEvent::RuntimeProofConsumed,
+            "runtime_proof",
+        )?;
+    }
     let runtime_context = stored
         .map(|proof| proof.runtime_context)
         .unwrap_or_default();
 
     execute_recipe(
+        conn,
         recipe,
         context,
         &runtime_context,
-        policy_settings,
         health_external_transmission_enabled,
     )
 }
+
+/// Runs a recipe against its encrypted persisted state and records the outcome.
+///
+/// Stored `state_kv` values for the recipe are loaded under `kek` and overlaid
+/// by any values the host already put in `context.state`. The run's log, or a
+/// `failed` log carrying the error code, is appended to `execution_logs`.
+pub fn execute_recipe_recorded(
+    conn: &Connection,
+    kek: &KeyEncryptionKey,
+    recipe: &RecipeModel,
+    context: &ExecutionContext,
+    runtime_context: &SensitiveRuntimeContext,
+    health_external_transmission_enabled: bool,
+) -> RuntimeResult<ExecutionResult> {
+    let mut state = StateStore::load(conn, kek, &context.metadata.recipe_id)?;
+    for (key, value) in &context.state {
+        state.set(key.clone(), value.clone());
+    }
+    let mut persisted = context.clone();
+    persisted.state = state.values().clone();
+
+    let result = execute_recipe(
+        conn,
+        recipe,
+        &persisted,
+        runtime_context,
+        health_external_transmission_enabled,
+    );
+    let log = match &result {
+        Ok(result) => result.log.clone(),
+        Err(err) => ExecutionLog {
+            recipe_id: context.metadata.recipe_id.clone(),
+            run_id: context.metadata.run_id.clone(),
+            status: "failed".to_string(),
+            sensitive_used: false,
+            reason_code: Some(failure_code(err)),
+            timestamp: Utc::now().to_rfc3339(),
+        },
+    };
+    append_execution_log(conn, kek, &log)?;
+    result
+}
+
+fn failure_code(err: &RuntimeError) -> String {
+    match err {
+        RuntimeError::PermissionDenied { code, .. } => code.as_str(),
+        RuntimeError::UserInitiationRequired => "USER_INITIATION_REQUIRED",
+        RuntimeError::SandboxViolation(_) => "SANDBOX_VIOLATION",
+        RuntimeError::SchemaValidation(_) | RuntimeError::SchemaViolation { .. } => "SCHEMA_VALIDATION",
+        RuntimeError::SignatureInvalid => "SIGNATURE_INVALID",
+        RuntimeError::UntrustedPublisher(_) => "UNTRUSTED_PUBLISHER",
+        RuntimeError::Package { .. } => "PACKAGE_INVALID",
+        RuntimeError::Connector(_) => "CONNECTOR_ERROR",
+        RuntimeError::Storage(_) => "STORAGE_ERROR",
+        RuntimeError::Crypto(_) => "CRYPTO_ERROR",
+        RuntimeError::AuditChainBroken { .. } => "AUDIT_CHAIN_BROKEN",
+        RuntimeError::Serialization(_) => "SERIALIZATION_ERROR",
+    }
+    .to_string()
+}
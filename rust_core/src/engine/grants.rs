@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::recipe::catalog::action_capability;
+use crate::recipe::manifest::{Manifest, PermissionSet};
+
+/// A user's recorded consent for one manifest permission of one recipe.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PermissionGrant {
+    pub recipe_id: String,
+    pub permission: String,
+    pub manifest_version: String,
+    pub granted_at: String,
+    pub expires_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+impl PermissionGrant {
+    /// Returns true when the grant is neither revoked nor expired at `now`.
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        if self.revoked_at.is_some() {
+            return false;
+        }
+        match &self.expires_at {
+            Some(expires_at) => DateTime::parse_from_rfc3339(expires_at)
+                .map(|expiry| expiry.with_timezone(&Utc) > now)
+                .unwrap_or(false),
+            None => true,
+        }
+    }
+}
+
+/// Maps an action type to the `PermissionSet` field that must be granted for it.
+pub fn permission_key_for_action(action_type: &str) -> Option<&'static str> {
+    action_capability(action_type).and_then(|capability| capability.permission)
+}
+
+/// Lists the permission keys a manifest declares, in `PermissionSet` field order.
+pub fn declared_permission_keys(permissions: &PermissionSet) -> Vec<&'static str> {
+    let declared = [
+        ("notification_send", permissions.notification_send),
+        ("network_request", permissions.network_request.is_some()),
+        ("file_access", permissions.file_access.is_some()),
+        ("clipboard_read", permissions.clipboard_read),
+        ("clipboard_write", permissions.clipboard_write),
+        ("hotkey_register", permissions.hotkey_register),
+        ("camera_capture", permissions.camera_capture.is_some()),
+        ("microphone_record", permissions.microphone_record.is_some()),
+        ("webcam_capture", permissions.webcam_capture.is_some()),
+        ("health_read", permissions.health_read.is_some()),
+        ("health_export", permissions.health_export),
+    ];
+    declared
+        .into_iter()
+        .filter(|(_, is_declared)| *is_declared)
+        .map(|(key, _)| key)
+        .collect()
+}
+
+/// Returns true when `grants` holds an active grant for `permission` given to
+/// this exact manifest id and version.
+///
+/// Grants pinned to another version do not count; `install_recipe` re-pins the
+/// grants an update keeps and revokes the ones whose permissions escalated.
+pub fn has_active_grant(grants: &[PermissionGrant], manifest: &Manifest, permission: &str, now: DateTime<Utc>) -> bool {
+    grants.iter().any(|grant| {
+        grant.recipe_id == manifest.id
+            && grant.manifest_version == manifest.version
+            && grant.permission == permission
+            && grant.is_active_at(now)
+    })
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::recipe::catalog::is_sensitive_action;
+
 /// Execution log record with sensitive usage marker.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ExecutionLog {
@@ -11,12 +13,12 @@ pub struct ExecutionLog {
     pub timestamp: String,
 }
 
+/// Indicates whether a single action touches camera, microphone, webcam or health data.
+pub fn is_sensitive_usage(action_type: &str) -> bool {
+    is_sensitive_action(action_type)
+}
+
 /// Indicates whether any action consumed Sensitive permission.
 pub fn detect_sensitive_usage(action_types: &[String]) -> bool {
-    action_types.iter().any(|action| {
-        matches!(
-            action.as_str(),
-            "camera.capture" | "microphone.record" | "webcam.capture" | "health.read" | "health.export"
-        )
-    })
+    action_types.iter().any(|action| is_sensitive_usage(action))
 }
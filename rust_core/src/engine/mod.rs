@@ -1,9 +1,12 @@
 pub mod evaluator;
 pub mod executor;
+pub mod grants;
 pub mod logging;
+pub mod network;
 pub mod permission;
 pub mod policy;
 pub mod risk;
 pub mod sandbox;
 pub mod scheduler;
 pub mod state;
+pub mod template;
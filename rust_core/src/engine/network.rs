@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use url::{Host, Url};
+
+use crate::engine::sandbox::{endpoint_allows, host_matches, is_private_host, normalized_host};
+use crate::recipe::manifest::PermissionSet;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Why an outbound request was made; every kind draws from the same budget.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkCallKind {
+    Request,
+    Retry,
+    Redirect,
+}
+
+/// One outbound request charged to a run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NetworkCall {
+    pub action_id: String,
+    pub url: String,
+    pub kind: NetworkCallKind,
+}
+
+/// Per-run outbound request budget, owned by the executor.
+///
+/// The executor passes it to each connector it runs; connectors charge every
+/// attempt they send (first request, retry or redirect) with the URL actually
+/// requested, and the call is recorded against the step the executor started.
+#[derive(Debug, Clone)]
+pub struct NetworkBudget {
+    permissions: PermissionSet,
+    step: String,
+    calls: Vec<NetworkCall>,
+}
+
+fn sandbox_violation(reason: &str) -> RuntimeError {
+    RuntimeError::SandboxViolation(reason.to_string())
+}
+
+/// Validates a network URL against the allowlist.
+///
+/// `domains` entries match host names on the scheme's default port; endpoints add
+/// scheme, port and path constraints. IP literals need an exact endpoint, and
+/// private or local hosts one with `allow_private`.
+fn enforce_network_allowlist(url: &str, permission_set: &PermissionSet) -> RuntimeResult<()> {
+    let network = permission_set
+        .network_request
+        .as_ref()
+        .ok_or_else(|| RuntimeError::PermissionDenied {
+            reason: "network.request permission is missing".to_string(),
+            code: "NETWORK_PERMISSION_REQUIRED".to_string(),
+        })?;
+
+    let parsed = Url::parse(url).map_err(|err| RuntimeError::SandboxViolation(err.to_string()))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(sandbox_violation("only http and https URLs are allowed"));
+    }
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return Err(sandbox_violation("credentials in URLs are not allowed"));
+    }
+    let host = normalized_host(&parsed).ok_or_else(|| sandbox_violation("missing host"))?;
+
+    let private = is_private_host(&host);
+    let by_domain = matches!(host, Host::Domain(_))
+        && !private
+        && parsed.port().is_none()
+        && network.domains.iter().any(|domain| host_matches(domain, &host));
+    let by_endpoint = network
+        .endpoints
+        .iter()
+        .any(|endpoint| endpoint_allows(endpoint, &parsed, &host));
+    if !by_domain && !by_endpoint {
+        return Err(sandbox_violation(if private {
+            "private network hosts need an endpoint with allow_private"
+        } else {
+            "domain not in allowlist"
+        }));
+    }
+    Ok(())
+}
+
+impl NetworkBudget {
+    pub fn for_run(permissions: &PermissionSet) -> Self {
+        Self {
+            permissions: permissions.clone(),
+            step: String::new(),
+            calls: Vec::new(),
+        }
+    }
+
+    /// Attributes the following charges to flow step `action_id`.
+    pub(crate) fn start_step(&mut self, action_id: &str) {
+        self.step = action_id.to_string();
+    }
+
+    /// `network_request.max_calls`, or zero when the manifest declares no network access.
+    pub fn max_calls(&self) -> u32 {
+        self.permissions
+            .network_request
+            .as_ref()
+            .map(|network| network.max_calls)
+            .unwrap_or(0)
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.max_calls().saturating_sub(self.calls.len() as u32)
+    }
+
+    /// Checks `url` against the allowlist and records the call, or fails once `max_calls` are spent.
+    ///
+    /// Call this before every attempt, with the resolved URL about to be requested.
+    pub fn charge(&mut self, url: &str, kind: NetworkCallKind) -> RuntimeResult<()> {
+        if self.permissions.network_request.is_some() && self.remaining() == 0 {
+            return Err(RuntimeError::PermissionDenied {
+                reason: format!("run made {} of {} allowed network calls", self.calls.len(), self.max_calls()),
+                code: "NETWORK_BUDGET_EXHAUSTED".to_string(),
+            });
+        }
+        enforce_network_allowlist(url, &self.permissions)?;
+        self.calls.push(NetworkCall {
+            action_id: self.step.clone(),
+            url: url.to_string(),
+            kind,
+        });
+        Ok(())
+    }
+
+    pub fn calls(&self) -> &[NetworkCall] {
+        &self.calls
+    }
+
+    /// Calls charged by `action_id`, in order.
+    pub fn calls_for(&self, action_id: &str) -> Vec<NetworkCall> {
+        self.calls
+            .iter()
+            .filter(|call| call.action_id == action_id)
+            .cloned()
+            .collect()
+    }
+}
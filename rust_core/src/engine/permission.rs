@@ -1,55 +1,36 @@
+use chrono::Utc;
+
+use crate::engine::grants::{declared_permission_keys, has_active_grant, permission_key_for_action, PermissionGrant};
 use crate::engine::policy::{PolicySettings, SensitiveRuntimeContext, TriggerClass};
 use crate::engine::risk::RiskLevel;
+use crate::engine::sandbox::enforce_file_ops;
+use crate::recipe::catalog::{action_capability, is_sensitive_action};
 use crate::recipe::flow::ActionNode;
 use crate::recipe::manifest::Manifest;
 use crate::types::errors::{RuntimeError, RuntimeResult};
 
-fn action_is_sensitive(action_type: &str) -> bool {
-    matches!(
-        action_type,
-        "camera.capture" | "microphone.record" | "webcam.capture" | "health.read"
-    )
-}
-
-fn action_requires_user_initiation(action_type: &str) -> bool {
-    matches!(
-        action_type,
-        "camera.capture" | "microphone.record" | "webcam.capture" | "health.read"
-    )
+pub(crate) fn action_requires_user_initiation(action_type: &str) -> bool {
+    action_capability(action_type).is_some_and(|capability| capability.requires_user_initiation)
 }
 
 fn action_requires_visible_capture_ui(action_type: &str) -> bool {
-    matches!(
-        action_type,
-        "camera.capture" | "microphone.record" | "webcam.capture"
-    )
+    action_capability(action_type).is_some_and(|capability| capability.requires_visible_ui)
 }
 
 fn manifest_declares_action_permission(manifest: &Manifest, action_type: &str) -> bool {
-    match action_type {
-        "camera.capture" => manifest.permissions.camera_capture.is_some(),
-        "microphone.record" => manifest.permissions.microphone_record.is_some(),
-        "webcam.capture" => manifest.permissions.webcam_capture.is_some(),
-        "health.read" => manifest.permissions.health_read.is_some(),
-        "health.export" => manifest.permissions.health_export,
-        "notification.send" => manifest.permissions.notification_send,
-        "clipboard.read" => manifest.permissions.clipboard_read,
-        "clipboard.write" => manifest.permissions.clipboard_write,
-        "http.request" => manifest.permissions.network_request.is_some(),
-        "file.read" | "file.write" | "file.move" | "file.rename" => {
-            manifest.permissions.file_access.is_some()
-        }
-        _ => true,
+    match permission_key_for_action(action_type) {
+        Some(permission) => declared_permission_keys(&manifest.permissions).contains(&permission),
+        None => true,
     }
 }
 
 /// Validates manifest/flow risk consistency.
 pub fn validate_manifest_risk(manifest: &Manifest, actions: &[ActionNode]) -> RuntimeResult<()> {
-    let flow_has_sensitive = actions.iter().any(|action| action_is_sensitive(&action.action_type));
+    let flow_has_sensitive = actions.iter().any(|action| is_sensitive_action(&action.action_type));
     let permission_has_sensitive = manifest.permissions.uses_sensitive();
 
     if flow_has_sensitive || permission_has_sensitive {
-        if manifest.risk_level != RiskLevel::Sensitive {
+        if manifest.risk_level < RiskLevel::Sensitive {
             return Err(RuntimeError::PermissionDenied {
                 reason: "sensitive capabilities require Sensitive risk level".to_string(),
                 code: "RISK_LEVEL_MISMATCH".to_string(),
@@ -65,6 +46,17 @@ pub fn validate_manifest_risk(manifest: &Manifest, actions: &[ActionNode]) -> Ru
     Ok(())
 }
 
+/// Blocks Restricted recipes unless the active policy explicitly allows them.
+pub fn enforce_risk_level_policy(manifest: &Manifest, policy: &PolicySettings) -> RuntimeResult<()> {
+    if manifest.risk_level == RiskLevel::Restricted && !policy.allow_restricted_recipes {
+        return Err(RuntimeError::PermissionDenied {
+            reason: "restricted recipes are disabled by policy".to_string(),
+            code: "RESTRICTED_NOT_ALLOWED".to_string(),
+        });
+    }
+    Ok(())
+}
+
 /// Enforces runtime invocation constraints for each action.
 pub fn enforce_action_permission(
     manifest: &Manifest,
@@ -72,6 +64,7 @@ pub fn enforce_action_permission(
     trigger_class: &TriggerClass,
     runtime_context: &SensitiveRuntimeContext,
     policy_settings: &PolicySettings,
+    grants: &[PermissionGrant],
     health_external_transmission_enabled: bool,
 ) -> RuntimeResult<()> {
     if !manifest_declares_action_permission(manifest, action_type) {
@@ -80,6 +73,16 @@ pub fn enforce_action_permission(
             code: "ACTION_PERMISSION_NOT_DECLARED".to_string(),
         });
     }
+    enforce_file_ops(action_type, &manifest.permissions)?;
+
+    if let Some(permission) = permission_key_for_action(action_type) {
+        if !has_active_grant(grants, manifest, permission, Utc::now()) {
+            return Err(RuntimeError::PermissionDenied {
+                reason: format!("user has not granted {} for action {}", permission, action_type),
+                code: "PERMISSION_NOT_GRANTED".to_string(),
+            });
+        }
+    }
 
     if action_requires_user_initiation(action_type) {
         let must_be_user_initiated = action_type != "health.read" || policy_settings.health_read_requires_user_initiated;
@@ -91,13 +94,14 @@ pub fn enforce_action_permission(
         }
     }
 
-    if action_requires_visible_capture_ui(action_type) && policy_settings.require_visible_capture_ui {
-        if !runtime_context.visible_capture_ui {
-            return Err(RuntimeError::PermissionDenied {
-                reason: "visible capture UI is required for sensitive capture".to_string(),
-                code: "VISIBLE_CAPTURE_UI_REQUIRED".to_string(),
-            });
-        }
+    if action_requires_visible_capture_ui(action_type)
+        && policy_settings.require_visible_capture_ui
+        && !runtime_context.visible_capture_ui
+    {
+        return Err(RuntimeError::PermissionDenied {
+            reason: "visible capture UI is required for sensitive capture".to_string(),
+            code: "VISIBLE_CAPTURE_UI_REQUIRED".to_string(),
+        });
     }
 
     if action_requires_visible_capture_ui(action_type)
@@ -23,7 +23,7 @@ impl TriggerClass {
 }
 
 /// Runtime proof values provided by host UI/session to authorize sensitive execution.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SensitiveRuntimeContext {
     pub ui_session_active: bool,
     pub confirmation_token_exists: bool,
@@ -31,17 +31,6 @@ pub struct SensitiveRuntimeContext {
     pub is_background_execution: bool,
 }
 
-impl Default for SensitiveRuntimeContext {
-    fn default() -> Self {
-        Self {
-            ui_session_active: false,
-            confirmation_token_exists: false,
-            visible_capture_ui: false,
-            is_background_execution: false,
-        }
-    }
-}
-
 /// Serialized token proof submitted by host UI before sensitive execution.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SensitiveTokenPayload {
@@ -88,12 +77,17 @@ pub fn parse_runtime_proof_payload(
 }
 
 /// Runtime policy toggles for enterprise and sensitive data export.
+///
+/// Missing fields deserialize to their defaults so older stored rows keep loading.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
 pub struct PolicySettings {
     pub allow_health_export: bool,
     pub require_visible_capture_ui: bool,
     pub block_background_capture: bool,
     pub health_read_requires_user_initiated: bool,
+    /// Lets enterprise-signed `Restricted` recipes run on this device.
+    pub allow_restricted_recipes: bool,
 }
 
 impl Default for PolicySettings {
@@ -103,6 +97,31 @@ impl Default for PolicySettings {
             require_visible_capture_ui: true,
             block_background_capture: true,
             health_read_requires_user_initiated: true,
+            allow_restricted_recipes: false,
         }
     }
 }
+
+impl PolicySettings {
+    /// Rejects combinations that would allow hidden background capture.
+    pub fn validate(&self) -> RuntimeResult<()> {
+        if !self.require_visible_capture_ui && !self.block_background_capture {
+            return Err(RuntimeError::PermissionDenied {
+                reason: "policy must either require visible capture UI or block background capture"
+                    .to_string(),
+                code: "POLICY_SETTINGS_INVALID".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// True when no setting is looser than in `other`, so applying `self` over
+    /// `other` cannot enable anything `other` blocks.
+    pub fn is_at_least_as_strict_as(&self, other: &PolicySettings) -> bool {
+        (!self.allow_health_export || other.allow_health_export)
+            && (self.require_visible_capture_ui || !other.require_visible_capture_ui)
+            && (self.block_background_capture || !other.block_background_capture)
+            && (self.health_read_requires_user_initiated || !other.health_read_requires_user_initiated)
+            && (!self.allow_restricted_recipes || other.allow_restricted_recipes)
+    }
+}
@@ -1,6 +1,10 @@
-use url::Url;
+use std::net::Ipv4Addr;
 
-use crate::recipe::manifest::PermissionSet;
+use url::{Host, Url};
+
+use crate::engine::template::static_prefix;
+use crate::recipe::catalog::{action_capability, file_accesses};
+use crate::recipe::manifest::{NetworkEndpoint, NetworkPermission, PermissionSet};
 use crate::types::errors::{RuntimeError, RuntimeResult};
 
 /// Resource constraints for a single run.
@@ -32,20 +36,131 @@ pub fn validate_action_budget(action_count: usize, limits: &SandboxLimits) -> Ru
     Ok(())
 }
 
-/// Ensures sandbox URI is under allowed roots and blocks traversal patterns.
-pub fn enforce_file_sandbox(uri: &str, permission_set: &PermissionSet) -> RuntimeResult<()> {
-    if !uri.starts_with("sandbox://") {
-        return Err(RuntimeError::SandboxViolation(
-            "only sandbox:// URIs are allowed".to_string(),
-        ));
+const SANDBOX_SCHEME: &str = "sandbox://";
+
+fn sandbox_violation(reason: &str) -> RuntimeError {
+    RuntimeError::SandboxViolation(reason.to_string())
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
     }
+}
 
-    if uri.contains("..") {
-        return Err(RuntimeError::SandboxViolation(
-            "path traversal is not allowed".to_string(),
-        ));
+fn percent_decode(text: &str) -> RuntimeResult<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let high = bytes.get(index + 1).copied().and_then(hex_value);
+            let low = bytes.get(index + 2).copied().and_then(hex_value);
+            match (high, low) {
+                (Some(high), Some(low)) => decoded.push(high << 4 | low),
+                _ => return Err(sandbox_violation("invalid percent-encoding in uri")),
+            }
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| sandbox_violation("uri is not valid UTF-8"))
+}
+
+/// Path segments of the text after `sandbox://`, normalized as `SandboxPath` describes.
+fn normalized_segments(rest: &str) -> RuntimeResult<Vec<String>> {
+    if rest.contains(['?', '#']) {
+        return Err(sandbox_violation("sandbox URIs cannot carry a query or fragment"));
+    }
+    let decoded = percent_decode(rest)?;
+    if decoded.contains('%') {
+        return Err(sandbox_violation("double percent-encoding is not allowed"));
+    }
+    if decoded.chars().any(char::is_control) {
+        return Err(sandbox_violation("control characters are not allowed"));
+    }
+
+    let mut segments: Vec<String> = Vec::new();
+    for segment in decoded.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if segments.pop().is_none() {
+                    return Err(sandbox_violation("path traversal is not allowed"));
+                }
+            }
+            _ => segments.push(segment.to_string()),
+        }
+    }
+    Ok(segments)
+}
+
+/// A `sandbox://` URI reduced to its normalized path segments.
+///
+/// Parsing percent-decodes once, treats backslashes as separators, drops empty
+/// and `.` segments and resolves `..`; climbing above the sandbox, leftover
+/// `%` (double encoding), control characters, queries and fragments are rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxPath {
+    segments: Vec<String>,
+}
+
+impl SandboxPath {
+    pub fn parse(uri: &str) -> RuntimeResult<Self> {
+        let rest = uri
+            .strip_prefix(SANDBOX_SCHEME)
+            .ok_or_else(|| sandbox_violation("only sandbox:// URIs are allowed"))?;
+        let segments = normalized_segments(rest)?;
+        if segments.is_empty() {
+            return Err(sandbox_violation("sandbox URI has no path"));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Path a possibly templated URI is known to stay in before it is rendered: the whole
+    /// URI when it holds no template, else the directory before the first `{{`. `None`
+    /// when the static text does not name a directory yet (`{{x}}`, `sandbox://{{x}}`).
+    pub(crate) fn static_directory(uri: &str) -> RuntimeResult<Option<Self>> {
+        let (static_part, templated) = static_prefix(uri);
+        if !templated {
+            return Self::parse(uri).map(Some);
+        }
+        let Some(rest) = static_part.strip_prefix(SANDBOX_SCHEME) else {
+            if SANDBOX_SCHEME.starts_with(static_part) {
+                return Ok(None);
+            }
+            return Err(sandbox_violation("only sandbox:// URIs are allowed"));
+        };
+        let directory = rest.rfind(['/', '\\']).map(|end| &rest[..end]).unwrap_or_default();
+        let segments = normalized_segments(directory)?;
+        Ok((!segments.is_empty()).then_some(Self { segments }))
     }
 
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    /// True when `self` is `root` or lies beneath it, compared segment by segment.
+    pub fn is_within(&self, root: &SandboxPath) -> bool {
+        self.segments.starts_with(&root.segments)
+    }
+}
+
+impl std::fmt::Display for SandboxPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", SANDBOX_SCHEME, self.segments.join("/"))
+    }
+}
+
+/// Ensures sandbox URI is under allowed roots and blocks traversal patterns.
+pub fn enforce_file_sandbox(uri: &str, permission_set: &PermissionSet) -> RuntimeResult<()> {
+    let path = SandboxPath::parse(uri)?;
+
     let roots = permission_set
         .file_access
         .as_ref()
@@ -55,7 +170,9 @@ pub fn enforce_file_sandbox(uri: &str, permission_set: &PermissionSet) -> Runtim
             code: "FILE_PERMISSION_REQUIRED".to_string(),
         })?;
 
-    let allowed = roots.iter().any(|root| uri.starts_with(root));
+    let allowed = roots
+        .iter()
+        .any(|root| SandboxPath::parse(root).is_ok_and(|root| path.is_within(&root)));
     if !allowed {
         return Err(RuntimeError::SandboxViolation(
             "uri is outside allowed roots".to_string(),
@@ -64,31 +181,158 @@ pub fn enforce_file_sandbox(uri: &str, permission_set: &PermissionSet) -> Runtim
     Ok(())
 }
 
-/// Validates network URL against allowlist and declared call caps.
-pub fn enforce_network_allowlist(url: &str, call_index: u32, permission_set: &PermissionSet) -> RuntimeResult<()> {
-    let network = permission_set
-        .network_request
+/// True when `ops` allow `op` for `action_type`, directly or through its catalog
+/// `combined_file_op` (a declared `move` covers both sides of `file.move`).
+pub(crate) fn file_op_declared(ops: &[String], action_type: &str, op: &str) -> bool {
+    let combined = action_capability(action_type).and_then(|capability| capability.combined_file_op);
+    ops.iter()
+        .any(|declared| declared == op || combined == Some(declared.as_str()))
+}
+
+fn require_file_op(action_type: &str, op: &str, permission_set: &PermissionSet) -> RuntimeResult<()> {
+    let ops = permission_set
+        .file_access
         .as_ref()
-        .ok_or_else(|| RuntimeError::PermissionDenied {
-            reason: "network.request permission is missing".to_string(),
-            code: "NETWORK_PERMISSION_REQUIRED".to_string(),
-        })?;
+        .map(|f| f.ops.as_slice())
+        .unwrap_or_default();
+    if !file_op_declared(ops, action_type, op) {
+        return Err(RuntimeError::PermissionDenied {
+            reason: format!("{} needs file_access op {}", action_type, op),
+            code: "FILE_OP_NOT_DECLARED".to_string(),
+        });
+    }
+    Ok(())
+}
 
-    if call_index >= network.max_calls {
-        return Err(RuntimeError::SandboxViolation(
-            "network max_calls exceeded".to_string(),
-        ));
+/// Ensures `file_access.ops` cover every access `action_type` always performs.
+pub fn enforce_file_ops(action_type: &str, permission_set: &PermissionSet) -> RuntimeResult<()> {
+    for access in file_accesses(action_type).iter().filter(|access| !access.optional) {
+        require_file_op(action_type, access.op, permission_set)?;
     }
+    Ok(())
+}
 
-    let parsed = Url::parse(url).map_err(|err| RuntimeError::SandboxViolation(err.to_string()))?;
-    let host = parsed
-        .host_str()
-        .ok_or_else(|| RuntimeError::SandboxViolation("missing host".to_string()))?;
-    let allowed = network.domains.iter().any(|domain| domain == host);
-    if !allowed {
-        return Err(RuntimeError::SandboxViolation(
-            "domain not in allowlist".to_string(),
-        ));
+/// Sandbox-checks every URI param of a file action against the roots and the op it needs.
+///
+/// Expects rendered params. Optional params (capture `output_uri`) are checked when
+/// present; a URI that still holds a `{{` template is rejected, since nothing renders it later.
+pub fn enforce_file_action(
+    action_type: &str,
+    params: &serde_json::Value,
+    permission_set: &PermissionSet,
+) -> RuntimeResult<()> {
+    enforce_file_ops(action_type, permission_set)?;
+    for access in file_accesses(action_type) {
+        let uri = match params.get(access.param).and_then(serde_json::Value::as_str) {
+            Some(uri) => uri,
+            None if access.optional => continue,
+            None => {
+                return Err(RuntimeError::SchemaValidation(format!(
+                    "{} missing {}",
+                    action_type, access.param
+                )))
+            }
+        };
+        if access.optional {
+            require_file_op(action_type, access.op, permission_set)?;
+        }
+        if static_prefix(uri).1 {
+            return Err(RuntimeError::SandboxViolation(format!(
+                "{} {} has an unresolved template",
+                action_type, access.param
+            )));
+        }
+        enforce_file_sandbox(uri, permission_set)?;
     }
     Ok(())
 }
+
+/// Host of `url` as matched against allowlists: IDNA-normalized, lowercase, without a trailing dot.
+pub(crate) fn normalized_host(url: &Url) -> Option<Host<String>> {
+    match url.host()? {
+        Host::Domain(domain) => Some(Host::Domain(domain.trim_end_matches('.').to_string())),
+        host => Some(host.to_owned()),
+    }
+}
+
+/// Localhost names and loopback, private, shared, link-local or unspecified addresses.
+pub(crate) fn is_private_host(host: &Host<String>) -> bool {
+    fn private_v4(ip: &Ipv4Addr) -> bool {
+        let [first, second, ..] = ip.octets();
+        ip.is_private()
+            || ip.is_loopback()
+            || ip.is_link_local()
+            || ip.is_unspecified()
+            || ip.is_broadcast()
+            || (first == 100 && (second & 0xc0) == 64)
+    }
+    match host {
+        Host::Domain(domain) => domain == "localhost" || domain.ends_with(".localhost"),
+        Host::Ipv4(ip) => private_v4(ip),
+        Host::Ipv6(ip) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || ip.to_ipv4_mapped().is_some_and(|mapped| private_v4(&mapped))
+        }
+    }
+}
+
+/// Matches an allowlist host pattern; `*.example.com` covers subdomains only and
+/// IP literals only match exactly.
+pub(crate) fn host_matches(pattern: &str, host: &Host<String>) -> bool {
+    let (wildcard, pattern) = match pattern.strip_prefix("*.") {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let Ok(pattern) = Host::parse(pattern.trim_end_matches('.')) else {
+        return false;
+    };
+    match (&pattern, host) {
+        (Host::Domain(pattern), Host::Domain(domain)) if wildcard => domain
+            .strip_suffix(pattern.as_str())
+            .is_some_and(|label| label.len() > 1 && label.ends_with('.')),
+        _ if wildcard => false,
+        _ => &pattern == host,
+    }
+}
+
+fn path_within(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    prefix.is_empty() || path == prefix || path.starts_with(&format!("{}/", prefix))
+}
+
+pub(crate) fn endpoint_allows(endpoint: &NetworkEndpoint, url: &Url, host: &Host<String>) -> bool {
+    let port = endpoint.port.or(match endpoint.scheme.as_str() {
+        "https" => Some(443),
+        "http" => Some(80),
+        _ => None,
+    });
+    endpoint.scheme == url.scheme()
+        && host_matches(&endpoint.host, host)
+        && url.port_or_known_default() == port
+        && endpoint
+            .path_prefix
+            .as_deref()
+            .is_none_or(|prefix| path_within(url.path(), prefix))
+        && (endpoint.allow_private || !is_private_host(host))
+}
+
+/// True when a `domains` entry or endpoint names `host`, ignoring scheme, port and path.
+pub(crate) fn host_listed(network: &NetworkPermission, host: &Host<String>) -> bool {
+    network
+        .domains
+        .iter()
+        .chain(network.endpoints.iter().map(|endpoint| &endpoint.host))
+        .any(|pattern| host_matches(pattern, host))
+}
+
+/// True when an endpoint opts `host` in to private network access.
+pub(crate) fn private_host_allowed(network: &NetworkPermission, host: &Host<String>) -> bool {
+    network
+        .endpoints
+        .iter()
+        .any(|endpoint| endpoint.allow_private && host_matches(&endpoint.host, host))
+}
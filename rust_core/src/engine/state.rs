@@ -1,14 +1,39 @@
 use std::collections::HashMap;
 
+use rusqlite::Connection;
+
+use crate::storage::crypto::KeyEncryptionKey;
+use crate::storage::records::{list_state, put_state};
 use crate::types::datavalue::DataValue;
+use crate::types::errors::RuntimeResult;
 
 /// In-memory state map scoped by recipe.
+///
+/// Persisted rows live encrypted in `state_kv`; `load` and `save` go through
+/// `storage::records` with the host-held KEK.
 #[derive(Debug, Default, Clone)]
 pub struct StateStore {
     values: HashMap<String, DataValue>,
 }
 
 impl StateStore {
+    /// Decrypts every stored value for `recipe_id`.
+    pub fn load(conn: &Connection, kek: &KeyEncryptionKey, recipe_id: &str) -> RuntimeResult<Self> {
+        Ok(Self {
+            values: list_state(conn, kek, recipe_id)?.into_iter().collect(),
+        })
+    }
+
+    /// Seals and upserts every value for `recipe_id`.
+    pub fn save(&self, conn: &Connection, kek: &KeyEncryptionKey, recipe_id: &str) -> RuntimeResult<()> {
+        let mut keys: Vec<&String> = self.values.keys().collect();
+        keys.sort();
+        for key in keys {
+            put_state(conn, kek, recipe_id, key, &self.values[key])?;
+        }
+        Ok(())
+    }
+
     pub fn get(&self, key: &str) -> Option<&DataValue> {
         self.values.get(key)
     }
@@ -16,4 +41,8 @@ impl StateStore {
     pub fn set(&mut self, key: impl Into<String>, value: DataValue) {
         self.values.insert(key.into(), value);
     }
+
+    pub fn values(&self) -> &HashMap<String, DataValue> {
+        &self.values
+    }
 }
@@ -0,0 +1,96 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::recipe::config::config_scope;
+use crate::types::context::ExecutionContext;
+use crate::types::datavalue::DataValue;
+
+/// Values `{{namespace.key}}` templates resolve to at the start of a run:
+/// `input.*`, `state.*`, `config.*` and the `metadata.*` fields the linter accepts.
+pub fn template_scope(
+    context: &ExecutionContext,
+    config: &BTreeMap<String, serde_json::Value>,
+) -> HashMap<String, DataValue> {
+    let mut scope: HashMap<String, DataValue> = config_scope(config);
+    for (key, value) in &context.input {
+        scope.insert(format!("input.{}", key), value.clone());
+    }
+    for (key, value) in &context.state {
+        scope.insert(format!("state.{}", key), value.clone());
+    }
+    let metadata = &context.metadata;
+    let trigger_class = serde_json::to_value(&metadata.trigger_class)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default();
+    for (key, value) in [
+        ("recipe_id", metadata.recipe_id.clone()),
+        ("run_id", metadata.run_id.clone()),
+        ("trigger", metadata.trigger.clone()),
+        ("trigger_class", trigger_class),
+        ("started_at", metadata.started_at.clone()),
+        ("device.platform", metadata.device.platform.clone()),
+        ("device.os_version", metadata.device.os_version.clone()),
+        ("device.app_version", metadata.device.app_version.clone()),
+    ] {
+        scope.insert(format!("metadata.{}", key), DataValue::Text(value));
+    }
+    scope
+}
+
+/// Text before the first `{{`, and whether a template follows.
+pub(crate) fn static_prefix(value: &str) -> (&str, bool) {
+    match value.find("{{") {
+        Some(index) => (&value[..index], true),
+        None => (value, false),
+    }
+}
+
+/// Text a value takes when substituted into a string param.
+fn template_text(value: &DataValue) -> String {
+    match value {
+        DataValue::Text(text) | DataValue::Url(text) | DataValue::DateTime(text) => text.clone(),
+        DataValue::FileRef(file) => file.uri.clone(),
+        DataValue::MediaRef(media) => media.file.uri.clone(),
+        DataValue::Number(number) => number.to_string(),
+        DataValue::Boolean(flag) => flag.to_string(),
+        DataValue::Json(json) => json.to_string(),
+        DataValue::List(items) => serde_json::to_string(items).unwrap_or_default(),
+        DataValue::Null => String::new(),
+    }
+}
+
+fn render_text(text: &str, scope: &HashMap<String, DataValue>) -> String {
+    let mut rendered = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        match scope.get(after[..end].trim()) {
+            Some(value) => rendered.push_str(&template_text(value)),
+            // Kept as written; the sandbox rejects file URIs that still hold one.
+            None => rendered.push_str(&rest[start..start + end + 4]),
+        }
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Substitutes templates in every string of `params`; unknown references are kept as written.
+pub fn render_params(params: &serde_json::Value, scope: &HashMap<String, DataValue>) -> serde_json::Value {
+    match params {
+        serde_json::Value::String(text) => serde_json::Value::String(render_text(text, scope)),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().map(|item| render_params(item, scope)).collect())
+        }
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), render_params(value, scope)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
@@ -69,6 +69,12 @@ pub fn last_error_message() -> Option<String> {
     }
 }
 
+/// Submits a sensitive runtime proof JSON payload from the host.
+///
+/// # Safety
+///
+/// `payload_json` must be null or point to a valid NUL-terminated C string
+/// that stays alive for the duration of the call.
 #[no_mangle]
 pub unsafe extern "C" fn arquent_submit_sensitive_runtime_proof(payload_json: *const c_char) -> i32 {
     if payload_json.is_null() {
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::security::canonical::canonicalize_json;
+use crate::security::hashing::sha256_hex;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+pub const ASSETS_MANIFEST_FILE: &str = "assets.json";
+pub const ASSETS_MANIFEST_VERSION: u32 = 1;
+
+/// One file under `assets/` as recorded in `assets.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct AssetEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// `assets.json`: the signed inventory of every file under `assets/`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct AssetsManifest {
+    pub version: u32,
+    pub files: Vec<AssetEntry>,
+}
+
+fn asset_error(path: &str, reason: impl Into<String>) -> RuntimeError {
+    RuntimeError::Package {
+        file: path.to_string(),
+        reason: reason.into(),
+    }
+}
+
+impl AssetsManifest {
+    /// Builds the manifest from asset contents keyed by package-relative path, sorted by path.
+    pub fn compute(assets: &BTreeMap<String, Vec<u8>>) -> Self {
+        Self {
+            version: ASSETS_MANIFEST_VERSION,
+            files: assets
+                .iter()
+                .map(|(path, bytes)| AssetEntry {
+                    path: path.clone(),
+                    size: bytes.len() as u64,
+                    sha256: sha256_hex(bytes),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn parse(bytes: &[u8]) -> RuntimeResult<Self> {
+        let manifest: Self =
+            serde_json::from_slice(bytes).map_err(|err| asset_error(ASSETS_MANIFEST_FILE, err.to_string()))?;
+        if manifest.version != ASSETS_MANIFEST_VERSION {
+            return Err(asset_error(
+                ASSETS_MANIFEST_FILE,
+                format!("unsupported assets manifest version {}", manifest.version),
+            ));
+        }
+        Ok(manifest)
+    }
+
+    /// Pretty JSON written to `assets.json`.
+    pub fn to_json_pretty(&self) -> RuntimeResult<String> {
+        let mut text =
+            serde_json::to_string_pretty(self).map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+        text.push('\n');
+        Ok(text)
+    }
+
+    /// Hash fed into the package digest: sha256 of the JCS form, independent of file formatting.
+    pub fn hash(&self) -> RuntimeResult<String> {
+        let value = serde_json::to_value(self).map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+        Ok(sha256_hex(canonicalize_json(&value)?.as_bytes()))
+    }
+
+    /// Checks one asset's size and hash against its entry.
+    pub fn verify_asset(&self, path: &str, bytes: &[u8]) -> RuntimeResult<()> {
+        let entry = self
+            .files
+            .iter()
+            .find(|entry| entry.path == path)
+            .ok_or_else(|| asset_error(path, "asset is not listed in assets.json"))?;
+        if entry.size != bytes.len() as u64 {
+            return Err(asset_error(
+                path,
+                format!("size {} does not match assets.json ({})", bytes.len(), entry.size),
+            ));
+        }
+        if entry.sha256 != sha256_hex(bytes) {
+            return Err(asset_error(path, "sha256 does not match assets.json"));
+        }
+        Ok(())
+    }
+
+    /// Requires an exact match: every listed asset present and intact, nothing unlisted.
+    pub fn verify_all(&self, assets: &BTreeMap<String, Vec<u8>>) -> RuntimeResult<()> {
+        let mut seen = std::collections::BTreeSet::new();
+        for entry in &self.files {
+            if !seen.insert(entry.path.as_str()) {
+                return Err(asset_error(&entry.path, "asset is listed more than once in assets.json"));
+            }
+            let bytes = assets
+                .get(&entry.path)
+                .ok_or_else(|| asset_error(&entry.path, "asset listed in assets.json is missing"))?;
+            self.verify_asset(&entry.path, bytes)?;
+        }
+        if let Some(extra) = assets.keys().find(|path| !seen.contains(path.as_str())) {
+            return Err(asset_error(extra, "asset is not listed in assets.json"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::recipe::flow::RecipeFlow;
+use crate::recipe::manifest::Manifest;
+use crate::recipe::package::{FLOW_FILE, MANIFEST_FILE};
+use crate::recipe::schema::{parse_strict, validate_flow_params_in};
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Format a recipe document is written in. JSON is the canonical form that is
+/// hashed and signed; YAML and TOML are authoring formats that allow comments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl SourceFormat {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+            Self::Toml => "toml",
+        }
+    }
+}
+
+fn package_error(file: &str, reason: impl ToString) -> RuntimeError {
+    RuntimeError::Package {
+        file: file.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn source_value(text: &str, format: SourceFormat, file: &str) -> RuntimeResult<serde_json::Value> {
+    match format {
+        SourceFormat::Json => serde_json::from_str(text).map_err(|err| package_error(file, err)),
+        SourceFormat::Yaml => serde_yaml::from_str(text).map_err(|err| package_error(file, err)),
+        SourceFormat::Toml => toml::from_str(text).map_err(|err| package_error(file, err)),
+    }
+}
+
+/// Strictly parses a manifest written in any source format.
+pub fn parse_manifest_source(text: &str, format: SourceFormat) -> RuntimeResult<Manifest> {
+    let file = format!("manifest.{}", format.extension());
+    parse_strict(&source_value(text, format, &file)?, &file, "")
+}
+
+/// Strictly parses a flow, including action params, written in any source format.
+pub fn parse_flow_source(text: &str, format: SourceFormat) -> RuntimeResult<RecipeFlow> {
+    let file = format!("flow.{}", format.extension());
+    let flow: RecipeFlow = parse_strict(&source_value(text, format, &file)?, &file, "")?;
+    validate_flow_params_in(&flow, &file)?;
+    Ok(flow)
+}
+
+/// Renders a recipe document in `format`; JSON output is the canonical pretty form.
+///
+/// TOML has no null, so absent optional fields are omitted and read back as
+/// `None`; null values inside action params cannot be written as TOML.
+pub fn render_source<T: Serialize>(document: &T, format: SourceFormat) -> RuntimeResult<String> {
+    let text = match format {
+        SourceFormat::Json => {
+            let mut text = serde_json::to_string_pretty(document)
+                .map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+            text.push('\n');
+            text
+        }
+        SourceFormat::Yaml => {
+            serde_yaml::to_string(document).map_err(|err| RuntimeError::Serialization(err.to_string()))?
+        }
+        SourceFormat::Toml => {
+            toml::to_string_pretty(document).map_err(|err| RuntimeError::Serialization(err.to_string()))?
+        }
+    };
+    Ok(text)
+}
+
+/// First authoring source for `stem` (`manifest` or `flow`) in `dir`, preferring YAML.
+fn find_source(dir: &Path, stem: &str) -> Option<(std::path::PathBuf, SourceFormat)> {
+    ["yaml", "yml", "toml"].into_iter().find_map(|extension| {
+        let path = dir.join(format!("{}.{}", stem, extension));
+        let format = SourceFormat::from_extension(extension)?;
+        path.is_file().then_some((path, format))
+    })
+}
+
+/// Returns true for a top-level authoring source name such as `manifest.yaml` or `flow.toml`.
+pub fn is_authoring_source(name: &str) -> bool {
+    name.split_once('.').is_some_and(|(stem, extension)| {
+        matches!(stem, "manifest" | "flow")
+            && SourceFormat::from_extension(extension).is_some_and(|format| format != SourceFormat::Json)
+    })
+}
+
+fn read_source(path: &Path) -> RuntimeResult<String> {
+    fs::read_to_string(path).map_err(|err| package_error(&path.display().to_string(), err))
+}
+
+fn write_file(dir: &Path, name: &str, contents: &str) -> RuntimeResult<()> {
+    fs::write(dir.join(name), contents).map_err(|err| package_error(name, err))
+}
+
+/// Regenerates `manifest.json` and `flow.json` from YAML/TOML sources in `dir`.
+///
+/// Documents without an authoring source keep their JSON file. Returns true
+/// when anything was compiled. Signing calls this first, so the signed bytes
+/// are always the canonical JSON.
+pub fn compile_authoring_sources(dir: &Path) -> RuntimeResult<bool> {
+    let mut compiled = false;
+    if let Some((path, format)) = find_source(dir, "manifest") {
+        let manifest = parse_manifest_source(&read_source(&path)?, format)?;
+        write_file(dir, MANIFEST_FILE, &render_source(&manifest, SourceFormat::Json)?)?;
+        compiled = true;
+    }
+    if let Some((path, format)) = find_source(dir, "flow") {
+        let flow = parse_flow_source(&read_source(&path)?, format)?;
+        write_file(dir, FLOW_FILE, &render_source(&flow, SourceFormat::Json)?)?;
+        compiled = true;
+    }
+    Ok(compiled)
+}
+
+/// Writes `manifest.<ext>` and `flow.<ext>` authoring sources from the package JSON.
+pub fn export_authoring_sources(dir: &Path, format: SourceFormat) -> RuntimeResult<()> {
+    let manifest = parse_manifest_source(&read_source(&dir.join(MANIFEST_FILE))?, SourceFormat::Json)?;
+    let flow = parse_flow_source(&read_source(&dir.join(FLOW_FILE))?, SourceFormat::Json)?;
+    write_file(
+        dir,
+        &format!("manifest.{}", format.extension()),
+        &render_source(&manifest, format)?,
+    )?;
+    write_file(dir, &format!("flow.{}", format.extension()), &render_source(&flow, format)?)
+}
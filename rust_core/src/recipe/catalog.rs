@@ -0,0 +1,244 @@
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+
+use crate::recipe::schema::{
+    parse_strict, versioned_schema, CaptureParams, ClipboardReadOutput, ClipboardReadParams, ClipboardWriteParams,
+    FileMoveParams, FileReadParams, FileRenameParams, FileWriteParams, HealthReadOutput, HealthReadParams,
+    HttpRequestParams, HttpResponseOutput, MediaOutput, MicrophoneRecordParams, NotificationSendParams, OkOutput,
+    StateGetOutput, StateGetParams, StateSetParams,
+};
+use crate::types::errors::RuntimeResult;
+
+type ValidateParams = fn(&serde_json::Value, &str, &str) -> RuntimeResult<()>;
+type SchemaFor = fn(&str) -> serde_json::Value;
+
+/// A sandbox URI param an action touches, with the `file_access.ops` entry it needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileAccess {
+    pub param: &'static str,
+    pub op: &'static str,
+    /// The param may be omitted, e.g. a capture without `output_uri`.
+    pub optional: bool,
+}
+
+const fn uri(param: &'static str, op: &'static str) -> FileAccess {
+    FileAccess {
+        param,
+        op,
+        optional: false,
+    }
+}
+
+/// Where captures and recordings are saved, when the step names a file.
+const CAPTURE_OUTPUT: &[FileAccess] = &[FileAccess {
+    param: "output_uri",
+    op: "write",
+    optional: true,
+}];
+
+/// Everything the runtime knows about one action type.
+///
+/// Permission gating, sensitive-usage logging, schema validation and export,
+/// and the linter all read this table instead of keeping their own lists.
+#[derive(Debug, Clone, Copy)]
+pub struct ActionCapability {
+    pub action_type: &'static str,
+    /// Connector implementing the action; `None` when the runtime handles it itself.
+    pub connector: Option<&'static str>,
+    /// `PermissionSet` key that must be declared and granted.
+    pub permission: Option<&'static str>,
+    /// Touches camera, microphone, webcam or health data.
+    pub sensitive: bool,
+    pub requires_user_initiation: bool,
+    pub requires_visible_ui: bool,
+    /// Runtime version that introduced the action.
+    pub since: &'static str,
+    /// Sandbox URI params, checked against `file_access` roots and ops.
+    pub file_accesses: &'static [FileAccess],
+    /// Single `file_access.ops` entry that also covers every access, e.g. `move`.
+    pub combined_file_op: Option<&'static str>,
+    params: Option<(ValidateParams, SchemaFor)>,
+    output: Option<SchemaFor>,
+}
+
+fn check<T: DeserializeOwned>(params: &serde_json::Value, file: &str, pointer: &str) -> RuntimeResult<()> {
+    parse_strict::<T>(params, file, pointer).map(drop)
+}
+
+const fn params<T: DeserializeOwned + JsonSchema>() -> Option<(ValidateParams, SchemaFor)> {
+    Some((check::<T>, versioned_schema::<T>))
+}
+
+const fn output<T: JsonSchema>() -> Option<SchemaFor> {
+    Some(versioned_schema::<T>)
+}
+
+const fn action(action_type: &'static str, connector: &'static str, permission: Option<&'static str>) -> ActionCapability {
+    ActionCapability {
+        action_type,
+        connector: Some(connector),
+        permission,
+        sensitive: false,
+        requires_user_initiation: false,
+        requires_visible_ui: false,
+        since: "0.3.0",
+        file_accesses: &[],
+        combined_file_op: None,
+        params: None,
+        output: None,
+    }
+}
+
+const fn capture(action_type: &'static str, connector: &'static str, permission: &'static str) -> ActionCapability {
+    ActionCapability {
+        file_accesses: CAPTURE_OUTPUT,
+        sensitive: true,
+        requires_user_initiation: true,
+        requires_visible_ui: true,
+        ..action(action_type, connector, Some(permission))
+    }
+}
+
+const ACTIONS: &[ActionCapability] = &[
+    ActionCapability {
+        params: params::<NotificationSendParams>(),
+        output: output::<OkOutput>(),
+        ..action("notification.send", "notification", Some("notification_send"))
+    },
+    ActionCapability {
+        params: params::<ClipboardReadParams>(),
+        output: output::<ClipboardReadOutput>(),
+        ..action("clipboard.read", "clipboard", Some("clipboard_read"))
+    },
+    ActionCapability {
+        params: params::<ClipboardWriteParams>(),
+        output: output::<OkOutput>(),
+        ..action("clipboard.write", "clipboard", Some("clipboard_write"))
+    },
+    ActionCapability {
+        params: params::<HttpRequestParams>(),
+        output: output::<HttpResponseOutput>(),
+        ..action("http.request", "http", Some("network_request"))
+    },
+    ActionCapability {
+        params: params::<FileReadParams>(),
+        output: output::<OkOutput>(),
+        file_accesses: &[uri("uri", "read")],
+        ..action("file.read", "file", Some("file_access"))
+    },
+    ActionCapability {
+        params: params::<FileWriteParams>(),
+        output: output::<OkOutput>(),
+        file_accesses: &[uri("uri", "write")],
+        ..action("file.write", "file", Some("file_access"))
+    },
+    ActionCapability {
+        params: params::<FileMoveParams>(),
+        output: output::<OkOutput>(),
+        file_accesses: &[uri("uri", "read"), uri("destination", "write")],
+        combined_file_op: Some("move"),
+        ..action("file.move", "file", Some("file_access"))
+    },
+    ActionCapability {
+        params: params::<FileRenameParams>(),
+        output: output::<OkOutput>(),
+        file_accesses: &[uri("uri", "rename")],
+        ..action("file.rename", "file", Some("file_access"))
+    },
+    ActionCapability {
+        params: params::<StateGetParams>(),
+        output: output::<StateGetOutput>(),
+        ..action("state.get", "kv", None)
+    },
+    ActionCapability {
+        params: params::<StateSetParams>(),
+        output: output::<OkOutput>(),
+        ..action("state.set", "kv", None)
+    },
+    ActionCapability {
+        params: params::<CaptureParams>(),
+        output: output::<MediaOutput>(),
+        ..capture("camera.capture", "camera", "camera_capture")
+    },
+    ActionCapability {
+        params: params::<MicrophoneRecordParams>(),
+        output: output::<MediaOutput>(),
+        ..capture("microphone.record", "microphone", "microphone_record")
+    },
+    ActionCapability {
+        params: params::<CaptureParams>(),
+        output: output::<MediaOutput>(),
+        ..capture("webcam.capture", "webcam", "webcam_capture")
+    },
+    ActionCapability {
+        sensitive: true,
+        requires_user_initiation: true,
+        params: params::<HealthReadParams>(),
+        output: output::<HealthReadOutput>(),
+        ..action("health.read", "health", Some("health_read"))
+    },
+    // Export is gated by the user toggle and enterprise policy, not a connector.
+    ActionCapability {
+        connector: None,
+        sensitive: true,
+        ..action("health.export", "health", Some("health_export"))
+    },
+];
+
+impl ActionCapability {
+    /// Checks `params` against the typed schema; actions without one accept any params.
+    pub fn validate_params(&self, params: &serde_json::Value, file: &str, pointer: &str) -> RuntimeResult<()> {
+        match self.params {
+            Some((validate, _)) => validate(params, file, pointer),
+            None => Ok(()),
+        }
+    }
+
+    pub fn params_json_schema(&self) -> Option<serde_json::Value> {
+        self.params
+            .map(|(_, schema)| schema(&format!("action:{}", self.action_type)))
+    }
+
+    pub fn output_json_schema(&self) -> Option<serde_json::Value> {
+        self.output
+            .map(|schema| schema(&format!("action:{}:output", self.action_type)))
+    }
+}
+
+/// Every action type known to this runtime.
+pub fn actions() -> &'static [ActionCapability] {
+    ACTIONS
+}
+
+/// Catalog entry for `action_type`, if this runtime knows it.
+pub fn action_capability(action_type: &str) -> Option<&'static ActionCapability> {
+    ACTIONS.iter().find(|capability| capability.action_type == action_type)
+}
+
+/// Action types implemented by the connector named `connector`.
+pub fn actions_for_connector(connector: &str) -> Vec<String> {
+    ACTIONS
+        .iter()
+        .filter(|capability| capability.connector == Some(connector))
+        .map(|capability| capability.action_type.to_string())
+        .collect()
+}
+
+/// Sandbox URI params `action_type` touches; empty for unknown and non-file actions.
+pub fn file_accesses(action_type: &str) -> &'static [FileAccess] {
+    action_capability(action_type)
+        .map(|capability| capability.file_accesses)
+        .unwrap_or_default()
+}
+
+/// True when `action_type` is known and touches sensitive data.
+pub fn is_sensitive_action(action_type: &str) -> bool {
+    action_capability(action_type).is_some_and(|capability| capability.sensitive)
+}
+
+/// True when any action gated by `permission` touches sensitive data.
+pub fn is_sensitive_permission(permission: &str) -> bool {
+    ACTIONS
+        .iter()
+        .any(|capability| capability.permission == Some(permission) && capability.sensitive)
+}
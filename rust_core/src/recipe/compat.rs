@@ -0,0 +1,179 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::recipe::catalog::action_capability;
+use crate::recipe::flow::RecipeFlow;
+use crate::recipe::manifest::Manifest;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Version of this runtime, compared against manifest version bounds.
+pub const RUNTIME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Semantic version per semver 2.0; build metadata is accepted and ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemVer {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<String>,
+}
+
+impl SemVer {
+    pub fn parse(text: &str) -> RuntimeResult<Self> {
+        let invalid = || RuntimeError::SchemaValidation(format!("invalid semantic version: {:?}", text));
+        let without_build = text.split_once('+').map(|(head, _)| head).unwrap_or(text);
+        let (core, pre) = match without_build.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (without_build, None),
+        };
+
+        let numbers = core
+            .split('.')
+            .map(|part| {
+                let valid = !part.is_empty()
+                    && part.bytes().all(|b| b.is_ascii_digit())
+                    && (part == "0" || !part.starts_with('0'));
+                if valid {
+                    part.parse::<u64>().map_err(|_| invalid())
+                } else {
+                    Err(invalid())
+                }
+            })
+            .collect::<RuntimeResult<Vec<_>>>()?;
+        let [major, minor, patch] = numbers[..] else {
+            return Err(invalid());
+        };
+
+        let pre = match pre {
+            Some(pre) => {
+                let identifiers: Vec<String> = pre.split('.').map(str::to_string).collect();
+                let valid = identifiers.iter().all(|id| {
+                    !id.is_empty()
+                        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                        && !(id.len() > 1 && id.starts_with('0') && id.bytes().all(|b| b.is_ascii_digit()))
+                });
+                if !valid {
+                    return Err(invalid());
+                }
+                identifiers
+            }
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+}
+
+fn compare_pre_identifier(left: &str, right: &str) -> Ordering {
+    match (left.parse::<u64>(), right.parse::<u64>()) {
+        (Ok(left), Ok(right)) => left.cmp(&right),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => left.cmp(right),
+    }
+}
+
+impl Ord for SemVer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => {
+                    for (left, right) in self.pre.iter().zip(&other.pre) {
+                        let ordering = compare_pre_identifier(left, right);
+                        if ordering != Ordering::Equal {
+                            return ordering;
+                        }
+                    }
+                    self.pre.len().cmp(&other.pre.len())
+                }
+            })
+    }
+}
+
+impl PartialOrd for SemVer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for SemVer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Runtime version that introduced `action_type`, if this runtime knows it.
+///
+/// Flows using an action newer than the running runtime are rejected even when
+/// the manifest under-declares `min_runtime_version`; actions this runtime
+/// does not know at all are rejected with `UNKNOWN_ACTION_TYPE`.
+pub fn action_min_runtime_version(action_type: &str) -> Option<&'static str> {
+    action_capability(action_type).map(|capability| capability.since)
+}
+
+fn too_old(reason: String) -> RuntimeError {
+    RuntimeError::PermissionDenied {
+        reason,
+        code: "RUNTIME_TOO_OLD".to_string(),
+    }
+}
+
+/// Checks manifest version bounds and per-action feature versions against `runtime_version`.
+pub fn check_runtime_compatibility(manifest: &Manifest, flow: &RecipeFlow, runtime_version: &str) -> RuntimeResult<()> {
+    let runtime = SemVer::parse(runtime_version)?;
+
+    let min = SemVer::parse(&manifest.min_runtime_version)?;
+    if runtime < min {
+        return Err(too_old(format!("recipe requires runtime {} or newer, running {}", min, runtime)));
+    }
+    if let Some(max_runtime_version) = &manifest.max_runtime_version {
+        let max = SemVer::parse(max_runtime_version)?;
+        if max < min {
+            return Err(RuntimeError::SchemaValidation(format!(
+                "max_runtime_version {} is below min_runtime_version {}",
+                max, min
+            )));
+        }
+        if runtime > max {
+            return Err(RuntimeError::PermissionDenied {
+                reason: format!("recipe supports runtime up to {}, running {}", max, runtime),
+                code: "RUNTIME_TOO_NEW".to_string(),
+            });
+        }
+    }
+
+    for action in &flow.actions {
+        let since = action_min_runtime_version(&action.action_type).ok_or_else(|| RuntimeError::PermissionDenied {
+            reason: format!(
+                "action {} is not known to runtime {}; it may need a newer runtime",
+                action.action_type, runtime
+            ),
+            code: "UNKNOWN_ACTION_TYPE".to_string(),
+        })?;
+        if runtime < SemVer::parse(since)? {
+            return Err(too_old(format!(
+                "action {} needs runtime {} or newer, running {}",
+                action.action_type, since, runtime
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// `check_runtime_compatibility` against this crate's version.
+pub fn check_current_runtime(manifest: &Manifest, flow: &RecipeFlow) -> RuntimeResult<()> {
+    check_runtime_compatibility(manifest, flow, RUNTIME_VERSION)
+}
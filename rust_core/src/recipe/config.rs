@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, HashMap};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::sandbox::{enforce_file_sandbox, SandboxPath};
+use crate::recipe::manifest::{Manifest, PermissionSet};
+use crate::types::datavalue::DataValue;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Value type of a manifest `config` parameter.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigParamType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    StringList,
+    /// A `sandbox://` URI chosen with the host's sandbox picker.
+    SandboxUri,
+}
+
+/// One user-settable recipe parameter, referenced from flows as `{{config.<key>}}`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigParam {
+    pub key: String,
+    #[serde(rename = "type")]
+    pub param_type: ConfigParamType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Used when the user sets nothing; a param without a default must be set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+    /// Allowed values for `string`, `integer` and `number` params.
+    #[serde(default, rename = "enum", skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<serde_json::Value>,
+    /// Inclusive bounds for `integer` and `number` params.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    /// Directory a `sandbox_uri` picker is limited to; must lie inside `file_access.roots`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+}
+
+fn invalid(key: &str, reason: impl std::fmt::Display) -> RuntimeError {
+    RuntimeError::SchemaValidation(format!("config.{}: {}", key, reason))
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+}
+
+fn is_within(uri: &str, root: &str) -> bool {
+    match (SandboxPath::parse(uri), SandboxPath::parse(root)) {
+        (Ok(uri), Ok(root)) => uri.is_within(&root),
+        _ => false,
+    }
+}
+
+impl ConfigParam {
+    /// Checks `value` against the type, enum, range and sandbox constraints.
+    pub fn check_value(&self, value: &serde_json::Value, permissions: &PermissionSet) -> RuntimeResult<()> {
+        let type_matches = match self.param_type {
+            ConfigParamType::String | ConfigParamType::SandboxUri => value.is_string(),
+            ConfigParamType::Integer => value.is_i64() || value.is_u64(),
+            ConfigParamType::Number => value.is_number(),
+            ConfigParamType::Boolean => value.is_boolean(),
+            ConfigParamType::StringList => value
+                .as_array()
+                .map(|items| items.iter().all(serde_json::Value::is_string))
+                .unwrap_or(false),
+        };
+        if !type_matches {
+            return Err(invalid(&self.key, format!("expected {:?} value, got {}", self.param_type, value)));
+        }
+        if !self.allowed.is_empty() && !self.allowed.contains(value) {
+            return Err(invalid(&self.key, format!("{} is not one of the allowed values", value)));
+        }
+        if let Some(number) = value.as_f64() {
+            if self.minimum.is_some_and(|minimum| number < minimum) || self.maximum.is_some_and(|maximum| number > maximum) {
+                return Err(invalid(&self.key, format!("{} is out of range", number)));
+            }
+        }
+        if self.param_type == ConfigParamType::SandboxUri {
+            let uri = value.as_str().unwrap_or_default();
+            enforce_file_sandbox(uri, permissions).map_err(|err| invalid(&self.key, err))?;
+            if let Some(root) = &self.root {
+                if !is_within(uri, root) {
+                    return Err(invalid(&self.key, format!("{} is outside {}", uri, root)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validates the manifest's `config` declarations themselves.
+pub fn validate_config_definition(manifest: &Manifest) -> RuntimeResult<()> {
+    let mut seen = Vec::new();
+    for param in &manifest.config {
+        if !is_valid_key(&param.key) {
+            return Err(invalid(&param.key, "keys use lowercase letters, digits and underscores"));
+        }
+        if seen.contains(&param.key.as_str()) {
+            return Err(invalid(&param.key, "declared twice"));
+        }
+        seen.push(&param.key);
+
+        let numeric = matches!(param.param_type, ConfigParamType::Integer | ConfigParamType::Number);
+        if !param.allowed.is_empty() && !numeric && param.param_type != ConfigParamType::String {
+            return Err(invalid(&param.key, "enum is only allowed on string, integer and number params"));
+        }
+        if (param.minimum.is_some() || param.maximum.is_some()) && !numeric {
+            return Err(invalid(&param.key, "minimum and maximum are only allowed on numeric params"));
+        }
+        if let (Some(minimum), Some(maximum)) = (param.minimum, param.maximum) {
+            if minimum > maximum {
+                return Err(invalid(&param.key, "minimum is greater than maximum"));
+            }
+        }
+        match (&param.root, param.param_type) {
+            (Some(root), ConfigParamType::SandboxUri) => {
+                enforce_file_sandbox(root, &manifest.permissions).map_err(|err| invalid(&param.key, err))?;
+            }
+            (Some(_), _) => return Err(invalid(&param.key, "root is only allowed on sandbox_uri params")),
+            (None, _) => {}
+        }
+        for value in param.allowed.iter().chain(&param.default) {
+            param.check_value(value, &manifest.permissions)?;
+        }
+    }
+    Ok(())
+}
+
+/// Validates user `values` and fills in defaults, returning every configured value.
+///
+/// Unknown keys are rejected and a param without a default must be present.
+pub fn resolve_config(
+    manifest: &Manifest,
+    values: &BTreeMap<String, serde_json::Value>,
+) -> RuntimeResult<BTreeMap<String, serde_json::Value>> {
+    if let Some(unknown) = values
+        .keys()
+        .find(|key| !manifest.config.iter().any(|param| &param.key == *key))
+    {
+        return Err(invalid(unknown, "not declared in the manifest"));
+    }
+    let mut resolved = BTreeMap::new();
+    for param in &manifest.config {
+        let value = values
+            .get(&param.key)
+            .or(param.default.as_ref())
+            .ok_or_else(|| invalid(&param.key, "a value is required"))?;
+        param.check_value(value, &manifest.permissions)?;
+        resolved.insert(param.key.clone(), value.clone());
+    }
+    Ok(resolved)
+}
+
+fn to_data_value(value: &serde_json::Value) -> DataValue {
+    match value {
+        serde_json::Value::String(text) => DataValue::Text(text.clone()),
+        serde_json::Value::Bool(flag) => DataValue::Boolean(*flag),
+        serde_json::Value::Number(number) => number
+            .as_f64()
+            .map(DataValue::Number)
+            .unwrap_or_else(|| DataValue::Json(value.clone())),
+        serde_json::Value::Array(items) => DataValue::List(items.iter().map(to_data_value).collect()),
+        serde_json::Value::Null => DataValue::Null,
+        serde_json::Value::Object(_) => DataValue::Json(value.clone()),
+    }
+}
+
+/// Resolved config as `config.<key>` entries for expression and template scopes.
+pub fn config_scope(resolved: &BTreeMap<String, serde_json::Value>) -> HashMap<String, DataValue> {
+    resolved
+        .iter()
+        .map(|(key, value)| (format!("config.{}", key), to_data_value(value)))
+        .collect()
+}
@@ -1,7 +1,9 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 /// A single action in execution order.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ActionNode {
     pub id: String,
     pub action_type: String,
@@ -9,8 +11,8 @@ pub struct ActionNode {
 }
 
 /// Condition expression tree.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(tag = "op", content = "args")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(tag = "op", content = "args", deny_unknown_fields)]
 pub enum Expression {
     Literal(bool),
     Eq { left: String, right: String },
@@ -21,14 +23,16 @@ pub enum Expression {
 }
 
 /// Trigger declaration.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct TriggerNode {
     pub trigger_type: String,
     pub params: serde_json::Value,
 }
 
 /// Full recipe flow model.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct RecipeFlow {
     pub trigger: TriggerNode,
     pub condition: Option<Expression>,
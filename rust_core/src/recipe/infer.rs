@@ -0,0 +1,180 @@
+use url::{Host, Url};
+
+use crate::connectors::http::{MAX_REDIRECTS, MAX_RETRIES};
+use crate::engine::grants::declared_permission_keys;
+use crate::engine::risk::RiskLevel;
+use crate::engine::sandbox::{is_private_host, normalized_host, SandboxPath};
+use crate::engine::template::static_prefix;
+use crate::recipe::catalog::{action_capability, is_sensitive_permission};
+use crate::recipe::flow::RecipeFlow;
+use crate::recipe::manifest::{
+    CameraPermission, FileAccessPermission, HealthReadPermission, MicrophonePermission, NetworkEndpoint,
+    NetworkPermission, PermissionSet,
+};
+
+/// Recording cap assumed when `microphone.record` omits `max_seconds`.
+pub const DEFAULT_MICROPHONE_MAX_SECONDS: u32 = 60;
+
+/// Aggregation assumed when `health.read` omits it: unaggregated samples.
+pub const DEFAULT_HEALTH_AGGREGATION: &str = "raw";
+
+fn push_unique(values: &mut Vec<String>, value: &str) {
+    if !values.iter().any(|existing| existing == value) {
+        values.push(value.to_string());
+    }
+}
+
+fn param_str<'a>(params: &'a serde_json::Value, key: &str) -> Option<&'a str> {
+    params.get(key).and_then(serde_json::Value::as_str)
+}
+
+/// `sandbox://<top-level dir>` of a URI, when its normalized static directory names it.
+fn sandbox_root(uri: &str) -> Option<String> {
+    let path = SandboxPath::static_directory(uri).ok()??;
+    Some(format!("sandbox://{}", path.segments().first()?))
+}
+
+/// Static part of a URL, unless the host itself is templated.
+fn static_url(url: &str) -> Option<Url> {
+    let (static_part, templated) = static_prefix(url);
+    let (_, after_scheme) = static_part.split_once("://")?;
+    if templated && !after_scheme.contains('/') {
+        return None;
+    }
+    Url::parse(static_part).ok()
+}
+
+/// Adds the narrowest allowlist entry for `url`: a `domains` host for public names on
+/// the default port, otherwise an endpoint (opting in to private hosts when needed).
+fn allow_url(url: &Url, domains: &mut Vec<String>, endpoints: &mut Vec<NetworkEndpoint>) {
+    let Some(host) = normalized_host(url) else {
+        return;
+    };
+    let private = is_private_host(&host);
+    if matches!(host, Host::Domain(_)) && !private && url.port().is_none() {
+        push_unique(domains, &host.to_string());
+        return;
+    }
+    let endpoint = NetworkEndpoint {
+        scheme: url.scheme().to_string(),
+        host: host.to_string(),
+        port: url.port(),
+        path_prefix: None,
+        allow_private: private,
+    };
+    if !endpoints.contains(&endpoint) {
+        endpoints.push(endpoint);
+    }
+}
+
+/// Smallest `PermissionSet` that covers what the flow's actions and trigger do.
+///
+/// Actions map to permission keys and sandbox URI params through `recipe::catalog`.
+/// Domains and file roots come from the literal part of params; values that are
+/// fully templated cannot be inferred and are left for the author to declare.
+/// `health.read` steps with different aggregations merge to `raw`. `max_calls`
+/// budgets every `http.request` step for all its retries and redirects.
+pub fn infer_permissions(flow: &RecipeFlow) -> PermissionSet {
+    let mut permissions = PermissionSet {
+        hotkey_register: flow.trigger.trigger_type == "trigger.hotkey",
+        ..PermissionSet::default()
+    };
+    let mut domains = Vec::new();
+    let mut endpoints = Vec::new();
+    let mut http_calls = 0u32;
+    let mut roots = Vec::new();
+    let mut ops = Vec::new();
+    let mut file_touched = false;
+
+    for action in &flow.actions {
+        let Some(capability) = action_capability(&action.action_type) else {
+            continue;
+        };
+        let params = &action.params;
+        for access in capability.file_accesses {
+            if let Some(uri) = param_str(params, access.param) {
+                file_touched = true;
+                push_unique(&mut ops, capability.combined_file_op.unwrap_or(access.op));
+                if let Some(root) = sandbox_root(uri) {
+                    push_unique(&mut roots, &root);
+                }
+            }
+        }
+        match capability.permission {
+            Some("network_request") => {
+                http_calls += 1;
+                if let Some(url) = param_str(params, "url").and_then(static_url) {
+                    allow_url(&url, &mut domains, &mut endpoints);
+                }
+            }
+            Some(key @ ("camera_capture" | "webcam_capture")) => {
+                let camera = Some(CameraPermission {
+                    mode: "user_initiated_only".to_string(),
+                });
+                if key == "camera_capture" {
+                    permissions.camera_capture = camera;
+                } else {
+                    permissions.webcam_capture = camera;
+                }
+            }
+            Some("microphone_record") => {
+                let max_seconds = params
+                    .get("max_seconds")
+                    .and_then(serde_json::Value::as_u64)
+                    .and_then(|seconds| u32::try_from(seconds).ok())
+                    .unwrap_or(DEFAULT_MICROPHONE_MAX_SECONDS);
+                let longest = permissions
+                    .microphone_record
+                    .as_ref()
+                    .map(|microphone| microphone.max_seconds.max(max_seconds))
+                    .unwrap_or(max_seconds);
+                permissions.microphone_record = Some(MicrophonePermission {
+                    max_seconds: longest,
+                    user_initiated_only: true,
+                });
+            }
+            Some("health_read") => {
+                let aggregation = param_str(params, "aggregation").unwrap_or(DEFAULT_HEALTH_AGGREGATION);
+                let health = permissions.health_read.get_or_insert_with(|| HealthReadPermission {
+                    types: Vec::new(),
+                    aggregation: aggregation.to_string(),
+                });
+                // Steps asking for different aggregations need the unaggregated samples.
+                if health.aggregation != aggregation {
+                    health.aggregation = DEFAULT_HEALTH_AGGREGATION.to_string();
+                }
+                let types = params.get("types").and_then(serde_json::Value::as_array);
+                for health_type in types.into_iter().flatten().filter_map(serde_json::Value::as_str) {
+                    push_unique(&mut health.types, health_type);
+                }
+            }
+            Some("notification_send") => permissions.notification_send = true,
+            Some("clipboard_read") => permissions.clipboard_read = true,
+            Some("clipboard_write") => permissions.clipboard_write = true,
+            Some("health_export") => permissions.health_export = true,
+            // `file_access` comes from `file_accesses` above.
+            _ => {}
+        }
+    }
+
+    if http_calls > 0 {
+        permissions.network_request = Some(NetworkPermission {
+            domains,
+            endpoints,
+            max_calls: http_calls * (1 + MAX_RETRIES + MAX_REDIRECTS),
+        });
+    }
+    if file_touched {
+        permissions.file_access = Some(FileAccessPermission { roots, ops });
+    }
+    permissions
+}
+
+/// Risk level a manifest declaring `permissions` must carry.
+pub fn implied_risk_level(permissions: &PermissionSet) -> RiskLevel {
+    if declared_permission_keys(permissions).into_iter().any(is_sensitive_permission) {
+        RiskLevel::Sensitive
+    } else {
+        RiskLevel::Standard
+    }
+}
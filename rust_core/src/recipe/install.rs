@@ -0,0 +1,62 @@
+use crate::connectors::registry::ConnectorRegistry;
+use crate::engine::permission::validate_manifest_risk;
+use crate::recipe::compat::{check_runtime_compatibility, RUNTIME_VERSION};
+use crate::recipe::config::validate_config_definition;
+use crate::recipe::model::RecipeModel;
+use crate::recipe::schema::validate_flow_params;
+use crate::security::keyring::VerifiedPublisher;
+use crate::security::validator::validate_restricted_source;
+use crate::types::context::DeviceMeta;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Host facts an install is checked against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstallContext {
+    pub runtime_version: String,
+    /// Same value as `DeviceMeta.platform`, e.g. `ios` or `desktop`.
+    pub platform: String,
+    /// Keyring result for the package being installed, if it was verified; it must
+    /// name the same recipe id and version (`VERIFIED_PUBLISHER_MISMATCH`).
+    pub verified_publisher: Option<VerifiedPublisher>,
+}
+
+impl InstallContext {
+    /// Context for this runtime build on the given device.
+    pub fn for_device(device: &DeviceMeta) -> Self {
+        Self {
+            runtime_version: RUNTIME_VERSION.to_string(),
+            platform: device.platform.clone(),
+            verified_publisher: None,
+        }
+    }
+
+    pub fn with_verified_publisher(mut self, verified_publisher: VerifiedPublisher) -> Self {
+        self.verified_publisher = Some(verified_publisher);
+        self
+    }
+}
+
+/// Checks that a recipe may be installed on this host.
+pub fn validate_install(
+    model: &RecipeModel,
+    context: &InstallContext,
+    registry: &ConnectorRegistry,
+) -> RuntimeResult<()> {
+    validate_flow_params(&model.flow)?;
+    validate_manifest_risk(&model.manifest, &model.flow.actions)?;
+    if let Some(verified) = &context.verified_publisher {
+        if !verified.covers(&model.manifest) {
+            return Err(RuntimeError::PermissionDenied {
+                reason: format!(
+                    "keyring result was verified for another package than {} {}",
+                    model.manifest.id, model.manifest.version
+                ),
+                code: "VERIFIED_PUBLISHER_MISMATCH".to_string(),
+            });
+        }
+    }
+    validate_restricted_source(&model.manifest, context.verified_publisher.as_ref())?;
+    validate_config_definition(&model.manifest)?;
+    check_runtime_compatibility(&model.manifest, &model.flow, &context.runtime_version)?;
+    registry.validate_recipe(model, &context.platform)
+}
@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::engine::grants::{declared_permission_keys, permission_key_for_action};
+use crate::engine::policy::TriggerClass;
+use crate::engine::sandbox::{
+    file_op_declared, host_listed, is_private_host, normalized_host, private_host_allowed, SandboxPath,
+};
+use crate::engine::template::static_prefix;
+use crate::recipe::catalog::{action_capability, file_accesses};
+use crate::recipe::config::ConfigParam;
+use crate::recipe::flow::Expression;
+use crate::recipe::infer::{infer_permissions, DEFAULT_HEALTH_AGGREGATION};
+use crate::recipe::manifest::{PermissionChangeKind, PermissionSet};
+use crate::recipe::model::RecipeModel;
+
+/// How serious a lint finding is; `Error` findings would fail install or run.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// One finding, located by a JSON pointer into the serialized `RecipeModel`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: String,
+    pub pointer: String,
+    pub message: String,
+}
+
+/// Trigger types this runtime knows, with the class their runs get.
+const TRIGGER_TYPES: &[(&str, TriggerClass)] = &[
+    ("trigger.manual", TriggerClass::UserInitiated),
+    ("trigger.hotkey", TriggerClass::UserInitiated),
+    ("trigger.widget_tap", TriggerClass::UserInitiated),
+    ("trigger.share_sheet", TriggerClass::UserInitiated),
+    ("trigger.schedule", TriggerClass::Passive),
+    ("trigger.file_watcher", TriggerClass::Passive),
+    ("trigger.clipboard_change", TriggerClass::Passive),
+];
+
+const METADATA_FIELDS: &[&str] = &[
+    "recipe_id",
+    "run_id",
+    "trigger",
+    "trigger_class",
+    "started_at",
+    "device.platform",
+    "device.os_version",
+    "device.app_version",
+];
+
+/// Class of runs started by `trigger_type`, if the trigger is known.
+pub fn trigger_class_for(trigger_type: &str) -> Option<TriggerClass> {
+    TRIGGER_TYPES
+        .iter()
+        .find(|(known, _)| *known == trigger_type)
+        .map(|(_, class)| class.clone())
+}
+
+fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+struct Linter<'a> {
+    model: &'a RecipeModel,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn push(&mut self, severity: Severity, code: &str, pointer: String, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code: code.to_string(),
+            pointer,
+            message,
+        });
+    }
+
+    fn check_trigger(&mut self) -> Option<TriggerClass> {
+        let trigger_type = &self.model.flow.trigger.trigger_type;
+        let class = trigger_class_for(trigger_type);
+        if class.is_none() {
+            self.push(
+                Severity::Error,
+                "UNKNOWN_TRIGGER_TYPE",
+                "/flow/trigger/trigger_type".to_string(),
+                format!("trigger type {} is not known to this runtime", trigger_type),
+            );
+        }
+        class
+    }
+
+    fn check_actions(&mut self, trigger_class: Option<&TriggerClass>) {
+        let declared = declared_permission_keys(&self.model.manifest.permissions);
+        let mut health_aggregation: Option<&str> = None;
+        for (index, action) in self.model.flow.actions.iter().enumerate() {
+            let pointer = format!("/flow/actions/{}", index);
+            let capability = action_capability(&action.action_type);
+            if capability.is_none() {
+                self.push(
+                    Severity::Error,
+                    "UNKNOWN_ACTION_TYPE",
+                    format!("{}/action_type", pointer),
+                    format!("action type {} is not known to this runtime", action.action_type),
+                );
+            }
+            if let Some(permission) = capability.and_then(|capability| capability.permission) {
+                if !declared.contains(&permission) {
+                    self.push(
+                        Severity::Error,
+                        "PERMISSION_MISSING",
+                        format!("{}/action_type", pointer),
+                        format!("action {} needs permissions.{}", action.action_type, permission),
+                    );
+                }
+            }
+            if let Some(connector) = capability.and_then(|capability| capability.connector) {
+                if !self.model.manifest.required_connectors.iter().any(|declared| declared == connector) {
+                    self.push(
+                        Severity::Error,
+                        "CONNECTOR_NOT_DECLARED",
+                        format!("{}/action_type", pointer),
+                        format!("action {} needs connector {} in required_connectors", action.action_type, connector),
+                    );
+                }
+            }
+            if capability.is_some_and(|capability| capability.requires_user_initiation)
+                && trigger_class == Some(&TriggerClass::Passive)
+            {
+                self.push(
+                    Severity::Error,
+                    "SENSITIVE_ACTION_ON_PASSIVE_TRIGGER",
+                    format!("{}/action_type", pointer),
+                    format!(
+                        "action {} requires a user-initiated trigger, not {}",
+                        action.action_type, self.model.flow.trigger.trigger_type
+                    ),
+                );
+            }
+            self.check_templates(&action.params, &format!("{}/params", pointer));
+            self.check_file_uris(action, &pointer);
+            if action.action_type == "http.request" {
+                self.check_url(action, &pointer);
+            }
+            if action.action_type == "health.read" {
+                let aggregation = action
+                    .params
+                    .get("aggregation")
+                    .and_then(serde_json::Value::as_str)
+                    .unwrap_or(DEFAULT_HEALTH_AGGREGATION);
+                match health_aggregation {
+                    Some(first) if first != aggregation => self.push(
+                        Severity::Warning,
+                        "HEALTH_AGGREGATION_CONFLICT",
+                        format!("{}/params/aggregation", pointer),
+                        format!(
+                            "health.read asks for {} but an earlier step asks for {}; one health_read permission covers both only as {}",
+                            aggregation, first, DEFAULT_HEALTH_AGGREGATION
+                        ),
+                    ),
+                    Some(_) => {}
+                    None => health_aggregation = Some(aggregation),
+                }
+            }
+        }
+    }
+
+    /// Compares the declared permissions with `infer_permissions` of the flow.
+    ///
+    /// Allowlists (domains, endpoints, roots, ops) are left to the per-step checks,
+    /// which understand patterns, and whole keys to `PERMISSION_MISSING` and
+    /// `PERMISSION_UNUSED`; this reports limits, modes and health fields.
+    fn check_inferred_permissions(&mut self) {
+        let declared = &self.model.manifest.permissions;
+        let mut inferred = infer_permissions(&self.model.flow);
+        if let (Some(inferred), Some(declared)) = (inferred.network_request.as_mut(), &declared.network_request) {
+            inferred.domains = declared.domains.clone();
+            inferred.endpoints = declared.endpoints.clone();
+        }
+        if let (Some(inferred), Some(declared)) = (inferred.file_access.as_mut(), &declared.file_access) {
+            inferred.roots = declared.roots.clone();
+            inferred.ops = declared.ops.clone();
+        }
+        for change in PermissionSet::diff(declared, &inferred).changes {
+            let (severity, code, relation) = match change.kind {
+                PermissionChangeKind::Widened => (Severity::Warning, "PERMISSION_UNDER_DECLARED", "narrower than"),
+                // Only the hotkey trigger needs a permission no step reports as missing.
+                PermissionChangeKind::Added if change.permission == "hotkey_register" => {
+                    (Severity::Warning, "PERMISSION_UNDER_DECLARED", "narrower than")
+                }
+                PermissionChangeKind::Narrowed => (Severity::Info, "PERMISSION_OVER_DECLARED", "wider than"),
+                _ => continue,
+            };
+            self.push(
+                severity,
+                code,
+                format!("/manifest/permissions/{}", change.permission),
+                format!(
+                    "permissions.{} is {} the flow needs ({})",
+                    change.permission, relation, change.detail
+                ),
+            );
+        }
+    }
+
+    fn check_unused_permissions(&mut self) {
+        let used: Vec<&str> = self
+            .model
+            .flow
+            .actions
+            .iter()
+            .filter_map(|action| permission_key_for_action(&action.action_type))
+            .collect();
+        let hotkey_trigger = self.model.flow.trigger.trigger_type == "trigger.hotkey";
+        for permission in declared_permission_keys(&self.model.manifest.permissions) {
+            let is_used = if permission == "hotkey_register" {
+                hotkey_trigger
+            } else {
+                used.contains(&permission)
+            };
+            if !is_used {
+                self.push(
+                    Severity::Warning,
+                    "PERMISSION_UNUSED",
+                    format!("/manifest/permissions/{}", permission),
+                    format!("permissions.{} is declared but no step uses it", permission),
+                );
+            }
+        }
+    }
+
+    fn check_templates(&mut self, value: &serde_json::Value, pointer: &str) {
+        match value {
+            serde_json::Value::String(text) => {
+                for problem in template_problems(text, &self.model.manifest.config) {
+                    self.push(Severity::Warning, "TEMPLATE_UNRESOLVED", pointer.to_string(), problem);
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    self.check_templates(item, &format!("{}/{}", pointer, index));
+                }
+            }
+            serde_json::Value::Object(map) => {
+                for (key, item) in map {
+                    self.check_templates(item, &format!("{}/{}", pointer, escape_token(key)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_file_uris(&mut self, action: &crate::recipe::flow::ActionNode, pointer: &str) {
+        let file_access = self.model.manifest.permissions.file_access.as_ref();
+        let roots = file_access.map(|access| access.roots.as_slice()).unwrap_or_default();
+        for access in file_accesses(&action.action_type) {
+            let uri = action.params.get(access.param).and_then(serde_json::Value::as_str);
+            if uri.is_none() && access.optional {
+                continue;
+            }
+            if let Some(file_access) = file_access {
+                if !file_op_declared(&file_access.ops, &action.action_type, access.op) {
+                    self.push(
+                        Severity::Error,
+                        "FILE_OP_NOT_DECLARED",
+                        format!("{}/params/{}", pointer, access.param),
+                        format!("action {} needs permissions.file_access.ops {}", action.action_type, access.op),
+                    );
+                }
+            }
+            let Some(uri) = uri else {
+                continue;
+            };
+            // Templated URIs are checked up to their static directory; runs check the rendered URI.
+            let message = match SandboxPath::static_directory(uri) {
+                Ok(None) => continue,
+                Ok(Some(path)) => {
+                    let templated = static_prefix(uri).1;
+                    let inside = roots.iter().filter_map(|root| SandboxPath::parse(root).ok()).any(|root| {
+                        path.is_within(&root) || (templated && root.is_within(&path))
+                    });
+                    if inside {
+                        continue;
+                    }
+                    format!("{} is outside permissions.file_access.roots", uri)
+                }
+                Err(err) => format!("{} is not a valid sandbox URI: {}", uri, err),
+            };
+            self.push(
+                Severity::Error,
+                "FILE_URI_OUTSIDE_ROOTS",
+                format!("{}/params/{}", pointer, access.param),
+                message,
+            );
+        }
+    }
+
+    fn check_url(&mut self, action: &crate::recipe::flow::ActionNode, pointer: &str) {
+        let Some(url) = action.params.get("url").and_then(serde_json::Value::as_str) else {
+            return;
+        };
+        let (static_part, templated) = static_prefix(url);
+        let Some((_, after_scheme)) = static_part.split_once("://") else {
+            return;
+        };
+        // A templated host cannot be checked statically.
+        if templated && !after_scheme.contains('/') {
+            return;
+        }
+        let host = Url::parse(static_part).ok().and_then(|parsed| normalized_host(&parsed));
+        let network = self.model.manifest.permissions.network_request.as_ref();
+        let listed = host
+            .as_ref()
+            .zip(network)
+            .is_some_and(|(host, network)| host_listed(network, host));
+        if !listed {
+            self.push(
+                Severity::Error,
+                "URL_OUTSIDE_DOMAINS",
+                format!("{}/params/url", pointer),
+                format!("{} is outside permissions.network_request.domains", url),
+            );
+            return;
+        }
+        let private_denied = host
+            .as_ref()
+            .zip(network)
+            .is_some_and(|(host, network)| is_private_host(host) && !private_host_allowed(network, host));
+        if private_denied {
+            self.push(
+                Severity::Error,
+                "PRIVATE_NETWORK_HOST",
+                format!("{}/params/url", pointer),
+                format!("{} needs a network_request endpoint with allow_private", url),
+            );
+        }
+    }
+
+    fn check_condition(&mut self) {
+        if let Some(condition) = &self.model.flow.condition {
+            if let Some(value) = constant_value(condition) {
+                self.push(
+                    Severity::Warning,
+                    "CONSTANT_CONDITION",
+                    "/flow/condition".to_string(),
+                    format!("condition is always {}", value),
+                );
+            }
+        }
+    }
+}
+
+/// Problems with `{{namespace.path}}` references in one string.
+fn template_problems(text: &str, config: &[ConfigParam]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            problems.push(format!("unterminated template in {:?}", text));
+            break;
+        };
+        let reference = after[..end].trim();
+        let resolves = match reference.split_once('.') {
+            Some(("input" | "state", key)) => !key.is_empty(),
+            Some(("metadata", field)) => METADATA_FIELDS.contains(&field),
+            Some(("config", key)) => config.iter().any(|param| param.key == key),
+            _ => false,
+        };
+        if !resolves {
+            problems.push(format!("template {{{{{}}}}} does not reference input, state, metadata or a declared config key", reference));
+        }
+        rest = &after[end + 2..];
+    }
+    problems
+}
+
+/// Value of a condition that does not depend on run data.
+fn constant_value(expression: &Expression) -> Option<bool> {
+    match expression {
+        Expression::Literal(value) => Some(*value),
+        Expression::Eq { left, right } => (left == right).then_some(true),
+        Expression::Exists { .. } => None,
+        Expression::Not(inner) => constant_value(inner).map(|value| !value),
+        Expression::And(items) => {
+            let values: Vec<Option<bool>> = items.iter().map(constant_value).collect();
+            if values.contains(&Some(false)) {
+                Some(false)
+            } else if values.iter().all(Option::is_some) {
+                Some(true)
+            } else {
+                None
+            }
+        }
+        Expression::Or(items) => {
+            let values: Vec<Option<bool>> = items.iter().map(constant_value).collect();
+            if values.contains(&Some(true)) {
+                Some(true)
+            } else if values.iter().all(Option::is_some) {
+                Some(false)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Static checks an author can run before publishing; never fails, only reports.
+pub fn lint_recipe(model: &RecipeModel) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        model,
+        diagnostics: Vec::new(),
+    };
+    let trigger_class = linter.check_trigger();
+    linter.check_actions(trigger_class.as_ref());
+    linter.check_unused_permissions();
+    linter.check_inferred_permissions();
+    linter.check_condition();
+    linter.diagnostics
+}
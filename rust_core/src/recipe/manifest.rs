@@ -1,46 +1,102 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::engine::grants::declared_permission_keys;
 use crate::engine::risk::RiskLevel;
+use crate::recipe::catalog::is_sensitive_permission;
+use crate::recipe::config::ConfigParam;
 
 /// Marketplace publisher metadata.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct PublisherMeta {
     pub id: String,
     pub display_name: String,
+    /// Self-declared by the package and never trusted; use `Keyring::verify_package`.
+    #[serde(default)]
     pub verified: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct NetworkPermission {
+    /// Host names or `*.example.com` patterns, reachable over http or https on the default port.
     pub domains: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub endpoints: Vec<NetworkEndpoint>,
     pub max_calls: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+fn default_endpoint_scheme() -> String {
+    "https".to_string()
+}
+
+/// Structured allowlist entry matching scheme, host, port and path prefix.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkEndpoint {
+    /// `https` or `http`.
+    #[serde(default = "default_endpoint_scheme")]
+    pub scheme: String,
+    /// Host name, `*.example.com` (subdomains only) or an exact IP literal.
+    pub host: String,
+    /// Defaults to the scheme's port.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Path the request must equal or lie beneath, compared by segment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_prefix: Option<String>,
+    /// Permits localhost and private, loopback or link-local addresses, which are denied otherwise.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_private: bool,
+}
+
+impl std::fmt::Display for NetworkEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}://{}", self.scheme, self.host)?;
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        if let Some(path_prefix) = &self.path_prefix {
+            write!(f, "{}", path_prefix)?;
+        }
+        if self.allow_private {
+            write!(f, " (private)")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct FileAccessPermission {
     pub roots: Vec<String>,
     pub ops: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct CameraPermission {
     pub mode: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct MicrophonePermission {
     pub max_seconds: u32,
     pub user_initiated_only: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct HealthReadPermission {
     pub types: Vec<String>,
     pub aggregation: String,
 }
 
 /// Declarative recipe permission contract.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct PermissionSet {
     pub notification_send: bool,
     pub network_request: Option<NetworkPermission>,
@@ -57,24 +113,197 @@ pub struct PermissionSet {
 
 impl PermissionSet {
     pub fn uses_sensitive(&self) -> bool {
-        self.camera_capture.is_some()
-            || self.microphone_record.is_some()
-            || self.webcam_capture.is_some()
-            || self.health_read.is_some()
+        declared_permission_keys(self).into_iter().any(is_sensitive_permission)
+    }
+
+    /// Compares two versions' permissions; `Added` and `Widened` entries are escalations.
+    pub fn diff(old: &PermissionSet, new: &PermissionSet) -> PermissionDiff {
+        let mut diff = PermissionDiff::default();
+        diff.flag("notification_send", old.notification_send, new.notification_send);
+        diff.option("network_request", &old.network_request, &new.network_request, |diff, old, new| {
+            diff.list("network_request", "domains", &old.domains, &new.domains);
+            let endpoints = |endpoints: &[NetworkEndpoint]| endpoints.iter().map(ToString::to_string).collect::<Vec<_>>();
+            diff.list("network_request", "endpoints", &endpoints(&old.endpoints), &endpoints(&new.endpoints));
+            diff.limit("network_request", "max_calls", old.max_calls, new.max_calls);
+        });
+        diff.option("file_access", &old.file_access, &new.file_access, |diff, old, new| {
+            diff.list("file_access", "roots", &old.roots, &new.roots);
+            diff.list("file_access", "ops", &old.ops, &new.ops);
+        });
+        diff.flag("clipboard_read", old.clipboard_read, new.clipboard_read);
+        diff.flag("clipboard_write", old.clipboard_write, new.clipboard_write);
+        diff.flag("hotkey_register", old.hotkey_register, new.hotkey_register);
+        diff.option("camera_capture", &old.camera_capture, &new.camera_capture, |diff, old, new| {
+            diff.camera_mode("camera_capture", old, new);
+        });
+        diff.option("microphone_record", &old.microphone_record, &new.microphone_record, |diff, old, new| {
+            diff.limit("microphone_record", "max_seconds", old.max_seconds, new.max_seconds);
+            if old.user_initiated_only != new.user_initiated_only {
+                let kind = if new.user_initiated_only {
+                    PermissionChangeKind::Narrowed
+                } else {
+                    PermissionChangeKind::Widened
+                };
+                diff.push(
+                    "microphone_record",
+                    kind,
+                    format!("user_initiated_only {} -> {}", old.user_initiated_only, new.user_initiated_only),
+                );
+            }
+        });
+        diff.option("webcam_capture", &old.webcam_capture, &new.webcam_capture, |diff, old, new| {
+            diff.camera_mode("webcam_capture", old, new);
+        });
+        diff.option("health_read", &old.health_read, &new.health_read, |diff, old, new| {
+            diff.list("health_read", "types", &old.types, &new.types);
+            if old.aggregation != new.aggregation {
+                diff.push(
+                    "health_read",
+                    PermissionChangeKind::Widened,
+                    format!("aggregation {} -> {}", old.aggregation, new.aggregation),
+                );
+            }
+        });
+        diff.flag("health_export", old.health_export, new.health_export);
+        diff
     }
 }
 
-/// Recipe manifest metadata with risk and policy declarations.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionChangeKind {
+    Added,
+    Removed,
+    Widened,
+    Narrowed,
+}
+
+/// One change to a `PermissionSet` field between two recipe versions.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PermissionChange {
+    /// `PermissionSet` field name, matching grant keys.
+    pub permission: String,
+    pub kind: PermissionChangeKind,
+    pub detail: String,
+}
+
+impl PermissionChange {
+    pub fn is_escalation(&self) -> bool {
+        matches!(self.kind, PermissionChangeKind::Added | PermissionChangeKind::Widened)
+    }
+}
+
+/// Result of `PermissionSet::diff`, in `PermissionSet` field order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PermissionDiff {
+    pub changes: Vec<PermissionChange>,
+}
+
+impl PermissionDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn has_escalation(&self) -> bool {
+        self.changes.iter().any(PermissionChange::is_escalation)
+    }
+
+    /// Permission keys that gained access and so need fresh consent.
+    pub fn escalated_permissions(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = Vec::new();
+        for change in self.changes.iter().filter(|change| change.is_escalation()) {
+            if !keys.contains(&change.permission.as_str()) {
+                keys.push(&change.permission);
+            }
+        }
+        keys
+    }
+
+    fn push(&mut self, permission: &str, kind: PermissionChangeKind, detail: String) {
+        self.changes.push(PermissionChange {
+            permission: permission.to_string(),
+            kind,
+            detail,
+        });
+    }
+
+    fn flag(&mut self, permission: &str, old: bool, new: bool) {
+        match (old, new) {
+            (false, true) => self.push(permission, PermissionChangeKind::Added, "declared".to_string()),
+            (true, false) => self.push(permission, PermissionChangeKind::Removed, "no longer declared".to_string()),
+            _ => {}
+        }
+    }
+
+    fn option<T>(&mut self, permission: &str, old: &Option<T>, new: &Option<T>, compare: impl FnOnce(&mut Self, &T, &T)) {
+        match (old, new) {
+            (None, Some(_)) => self.push(permission, PermissionChangeKind::Added, "declared".to_string()),
+            (Some(_), None) => self.push(permission, PermissionChangeKind::Removed, "no longer declared".to_string()),
+            (Some(old), Some(new)) => compare(self, old, new),
+            (None, None) => {}
+        }
+    }
+
+    fn list(&mut self, permission: &str, field: &str, old: &[String], new: &[String]) {
+        let added: Vec<&str> = new.iter().filter(|value| !old.contains(value)).map(String::as_str).collect();
+        let removed: Vec<&str> = old.iter().filter(|value| !new.contains(value)).map(String::as_str).collect();
+        if !added.is_empty() {
+            self.push(permission, PermissionChangeKind::Widened, format!("{} + {}", field, added.join(", ")));
+        }
+        if !removed.is_empty() {
+            self.push(permission, PermissionChangeKind::Narrowed, format!("{} - {}", field, removed.join(", ")));
+        }
+    }
+
+    fn limit(&mut self, permission: &str, field: &str, old: u32, new: u32) {
+        if new > old {
+            self.push(permission, PermissionChangeKind::Widened, format!("{} {} -> {}", field, old, new));
+        } else if new < old {
+            self.push(permission, PermissionChangeKind::Narrowed, format!("{} {} -> {}", field, old, new));
+        }
+    }
+
+    fn camera_mode(&mut self, permission: &str, old: &CameraPermission, new: &CameraPermission) {
+        if old.mode == new.mode {
+            return;
+        }
+        let kind = if new.mode == "user_initiated_only" {
+            PermissionChangeKind::Narrowed
+        } else {
+            PermissionChangeKind::Widened
+        };
+        self.push(permission, kind, format!("mode {} -> {}", old.mode, new.mode));
+    }
+}
+
+/// Recipe manifest metadata with risk and policy declarations.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct Manifest {
     pub id: String,
     pub name: String,
+    /// Marketplace listing text; not used by the runtime.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub usage: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     pub version: String,
     pub min_runtime_version: String,
+    /// Newest runtime the recipe supports; absent means no upper bound.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_runtime_version: Option<String>,
     pub required_connectors: Vec<String>,
     pub permissions: PermissionSet,
+    /// User-settable parameters, exposed to the flow as `{{config.<key>}}`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub config: Vec<ConfigParam>,
     pub risk_level: RiskLevel,
     pub user_initiated_required: bool,
     pub signature: Option<String>,
     pub publisher: Option<PublisherMeta>,
+    /// Package digest algorithm marker; absent means the legacy digest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest_version: Option<String>,
 }
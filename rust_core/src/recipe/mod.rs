@@ -1,4 +1,13 @@
+pub mod assets;
+pub mod authoring;
+pub mod catalog;
+pub mod compat;
+pub mod config;
 pub mod flow;
+pub mod infer;
+pub mod install;
+pub mod lint;
 pub mod manifest;
 pub mod model;
+pub mod package;
 pub mod schema;
@@ -0,0 +1,303 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path};
+
+use chrono::{DateTime, Utc};
+
+use crate::recipe::assets::{AssetsManifest, ASSETS_MANIFEST_FILE};
+use crate::recipe::authoring::is_authoring_source;
+use crate::recipe::model::RecipeModel;
+use crate::recipe::schema::parse_recipe_documents;
+use crate::security::hashing::sha256_hex;
+use crate::security::keyring::{Keyring, VerifiedPublisher};
+use crate::security::envelope::SignatureInfo;
+use crate::security::signature::{verify_package_signature_file, DigestVersion};
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const FLOW_FILE: &str = "flow.json";
+pub const SIGNATURE_FILE: &str = "signature.sig";
+pub const ASSETS_DIR: &str = "assets";
+
+/// Upper bound on the total uncompressed size of a zipped package.
+pub const MAX_PACKAGE_BYTES: u64 = 32 * 1024 * 1024;
+
+/// A `.recipepkg` read from disk, keeping the raw bytes needed for digests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecipePackage {
+    pub model: RecipeModel,
+    pub manifest_bytes: Vec<u8>,
+    pub flow_bytes: Vec<u8>,
+    /// Asset contents keyed by package-relative path, e.g. `assets/icon.png`.
+    pub assets: BTreeMap<String, Vec<u8>>,
+    /// Parsed `assets.json`, or the inventory computed from `assets/` when absent.
+    pub assets_manifest: AssetsManifest,
+    /// True when the package ships `assets.json`.
+    pub has_assets_manifest_file: bool,
+    pub assets_manifest_hash: String,
+    pub signature: Option<String>,
+}
+
+impl RecipePackage {
+    /// Returns an asset after re-checking it against the assets manifest.
+    pub fn asset(&self, path: &str) -> RuntimeResult<&[u8]> {
+        let bytes = self
+            .assets
+            .get(path)
+            .ok_or_else(|| package_error(path, "asset is missing"))?;
+        self.assets_manifest.verify_asset(path, bytes)?;
+        Ok(bytes)
+    }
+
+    /// Verifies `signature.sig` against the package digest.
+    pub fn verify_signature(&self, public_key_b64: &str) -> RuntimeResult<SignatureInfo> {
+        self.verify_signature_with(public_key_b64, |_| None)
+    }
+
+    /// Like `verify_signature`, resolving co-signer keys by key id.
+    pub fn verify_signature_with(
+        &self,
+        public_key_b64: &str,
+        resolve_key: impl Fn(&str) -> Option<String>,
+    ) -> RuntimeResult<SignatureInfo> {
+        let signature = self
+            .signature
+            .as_deref()
+            .ok_or_else(|| package_error(SIGNATURE_FILE, "signature file is missing"))?;
+        verify_package_signature_file(
+            public_key_b64,
+            signature,
+            &self.manifest_bytes,
+            &self.flow_bytes,
+            &self.assets_manifest_hash,
+            resolve_key,
+        )
+    }
+}
+
+fn package_error(file: &str, reason: impl Into<String>) -> RuntimeError {
+    RuntimeError::Package {
+        file: file.to_string(),
+        reason: reason.into(),
+    }
+}
+
+/// Computes the legacy assets manifest hash for packages without `assets.json`.
+///
+/// Matches `recipes/tools/_common.mjs`: sha256 of the compact JSON array of
+/// `{"path","sha256"}` entries sorted by path, or of `[]` without assets.
+pub fn assets_manifest_hash(assets: &BTreeMap<String, Vec<u8>>) -> String {
+    let entries: Vec<serde_json::Value> = assets
+        .iter()
+        .map(|(path, bytes)| serde_json::json!({"path": path, "sha256": sha256_hex(bytes)}))
+        .collect();
+    sha256_hex(serde_json::Value::Array(entries).to_string().as_bytes())
+}
+
+/// Reads every file under `dir`, keyed by its `/`-joined path relative to `root`.
+///
+/// Symlinks and other non-regular entries are rejected rather than followed, so
+/// a package cannot pull in files from outside its directory. Unknown top-level
+/// files are kept and rejected by `parse_package`, exactly as for zips.
+fn collect_dir_files(root: &Path, dir: &Path, files: &mut BTreeMap<String, Vec<u8>>) -> RuntimeResult<()> {
+    let entries = fs::read_dir(dir).map_err(|err| package_error(&dir.display().to_string(), err.to_string()))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|err| package_error(&dir.display().to_string(), err.to_string()))?
+            .path();
+        let relative = path
+            .strip_prefix(root)
+            .map_err(|err| package_error(&path.display().to_string(), err.to_string()))?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+        let file_type = fs::symlink_metadata(&path)
+            .map_err(|err| package_error(&relative, err.to_string()))?
+            .file_type();
+        if file_type.is_symlink() {
+            return Err(package_error(&relative, "symlinks are not allowed in packages"));
+        } else if file_type.is_dir() {
+            collect_dir_files(root, &path, files)?;
+        } else if file_type.is_file() {
+            let bytes = fs::read(&path).map_err(|err| package_error(&relative, err.to_string()))?;
+            files.insert(relative, bytes);
+        } else {
+            return Err(package_error(&relative, "not a regular file"));
+        }
+    }
+    Ok(())
+}
+
+fn read_dir_files(path: &Path) -> RuntimeResult<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    collect_dir_files(path, path, &mut files)?;
+    Ok(files)
+}
+
+fn read_zip_files(path: &Path) -> RuntimeResult<BTreeMap<String, Vec<u8>>> {
+    let display = path.display().to_string();
+    let file = fs::File::open(path).map_err(|err| package_error(&display, err.to_string()))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|err| package_error(&display, err.to_string()))?;
+
+    let mut files = BTreeMap::new();
+    let mut total: u64 = 0;
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|err| package_error(&display, err.to_string()))?;
+        let name = entry.name().to_string();
+        if entry.is_dir() {
+            continue;
+        }
+        if entry.is_symlink() {
+            return Err(package_error(&name, "symlinks are not allowed in packages"));
+        }
+        let enclosed = entry
+            .enclosed_name()
+            .filter(|enclosed| enclosed.components().all(|c| matches!(c, Component::Normal(_))))
+            .ok_or_else(|| package_error(&name, "zip entry escapes the package root"))?;
+        let relative = enclosed
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let mut bytes = Vec::new();
+        entry
+            .by_ref()
+            .take(MAX_PACKAGE_BYTES - total + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|err| package_error(&relative, err.to_string()))?;
+        total += bytes.len() as u64;
+        if total > MAX_PACKAGE_BYTES {
+            return Err(package_error(&relative, "package exceeds maximum uncompressed size"));
+        }
+        files.insert(relative, bytes);
+    }
+
+    strip_single_top_level_dir(files)
+}
+
+/// Accepts zips that wrap the package in one top-level folder, e.g. `foo.recipepkg/manifest.json`.
+fn strip_single_top_level_dir(files: BTreeMap<String, Vec<u8>>) -> RuntimeResult<BTreeMap<String, Vec<u8>>> {
+    if files.contains_key(MANIFEST_FILE) {
+        return Ok(files);
+    }
+    let prefix = match files.keys().next().and_then(|key| key.split_once('/')) {
+        Some((top, _)) => format!("{}/", top),
+        None => return Ok(files),
+    };
+    if !files.keys().all(|key| key.starts_with(&prefix)) {
+        return Ok(files);
+    }
+    Ok(files
+        .into_iter()
+        .map(|(key, bytes)| (key[prefix.len()..].to_string(), bytes))
+        .collect())
+}
+
+fn parse_package(mut files: BTreeMap<String, Vec<u8>>) -> RuntimeResult<RecipePackage> {
+    let manifest_bytes = files
+        .remove(MANIFEST_FILE)
+        .ok_or_else(|| package_error(MANIFEST_FILE, "file is missing"))?;
+    let flow_bytes = files
+        .remove(FLOW_FILE)
+        .ok_or_else(|| package_error(FLOW_FILE, "file is missing"))?;
+    let signature = files
+        .remove(SIGNATURE_FILE)
+        .map(|bytes| {
+            String::from_utf8(bytes)
+                .map(|text| text.trim().to_string())
+                .map_err(|_| package_error(SIGNATURE_FILE, "signature is not UTF-8"))
+        })
+        .transpose()?;
+    let assets_manifest_file = files.remove(ASSETS_MANIFEST_FILE);
+
+    let assets_prefix = format!("{}/", ASSETS_DIR);
+    if let Some(unexpected) = files.keys().find(|key| !key.starts_with(&assets_prefix)) {
+        return Err(package_error(unexpected, "unexpected file outside assets/"));
+    }
+
+    let model = parse_recipe_documents(&manifest_bytes, &flow_bytes)?;
+
+    let (assets_manifest, assets_manifest_hash) = match &assets_manifest_file {
+        Some(bytes) => {
+            let assets_manifest = AssetsManifest::parse(bytes)?;
+            assets_manifest.verify_all(&files)?;
+            let hash = assets_manifest.hash()?;
+            (assets_manifest, hash)
+        }
+        // Legacy exception: packages signed before `assets.json` existed are
+        // hashed over whatever assets they ship. `load` refuses this for
+        // `jcs-v1` packages, whose signers always write `assets.json`.
+        None => (AssetsManifest::compute(&files), assets_manifest_hash(&files)),
+    };
+
+    Ok(RecipePackage {
+        model,
+        manifest_bytes,
+        flow_bytes,
+        assets: files,
+        assets_manifest,
+        has_assets_manifest_file: assets_manifest_file.is_some(),
+        assets_manifest_hash,
+        signature,
+    })
+}
+
+/// Loads a `.recipepkg` from either its directory layout or a zipped single file.
+pub fn load(path: impl AsRef<Path>) -> RuntimeResult<RecipePackage> {
+    let path = path.as_ref();
+    let files = if path.is_dir() {
+        read_dir_files(path)?
+    } else if path.is_file() {
+        read_zip_files(path)?
+    } else {
+        return Err(package_error(&path.display().to_string(), "package not found"));
+    };
+    let package = parse_package(files)?;
+    if !package.has_assets_manifest_file
+        && DigestVersion::from_manifest(&package.manifest_bytes)? == DigestVersion::JcsV1
+    {
+        return Err(package_error(
+            ASSETS_MANIFEST_FILE,
+            "file is missing; jcs-v1 packages must list their assets",
+        ));
+    }
+    Ok(package)
+}
+
+/// Loads a package directory that may still hold YAML/TOML authoring sources
+/// next to the compiled JSON, as signing does. Published packages go through
+/// `load`, which rejects those files like any other unknown top-level file.
+/// `assets.json` is optional here because signing writes it.
+pub fn load_source_dir(path: impl AsRef<Path>) -> RuntimeResult<RecipePackage> {
+    let mut files = read_dir_files(path.as_ref())?;
+    files.retain(|name, _| !is_authoring_source(name));
+    parse_package(files)
+}
+
+/// Loads a package and verifies it against the keyring entry of its manifest publisher.
+pub fn load_trusted(
+    path: impl AsRef<Path>,
+    keyring: &Keyring,
+    at: DateTime<Utc>,
+) -> RuntimeResult<(RecipePackage, VerifiedPublisher)> {
+    let package = load(path)?;
+    let publisher = keyring.verify_package(&package, at)?;
+    Ok((package, publisher))
+}
+
+/// Loads a package and verifies `signature.sig` with the given publisher key.
+pub fn load_verified(path: impl AsRef<Path>, public_key_b64: &str) -> RuntimeResult<RecipePackage> {
+    let package = load(path)?;
+    package
+        .verify_signature(public_key_b64)
+        .map_err(|err| match err {
+            RuntimeError::SignatureInvalid => package_error(SIGNATURE_FILE, "signature does not match package digest"),
+            other => other,
+        })?;
+    Ok(package)
+}
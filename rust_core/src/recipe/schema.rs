@@ -1,30 +1,308 @@
-use crate::recipe::flow::ActionNode;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use schemars::{schema_for, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::recipe::catalog::{action_capability, actions};
+use crate::recipe::flow::{ActionNode, RecipeFlow};
+use crate::recipe::manifest::Manifest;
+use crate::recipe::model::RecipeModel;
 use crate::types::errors::{RuntimeError, RuntimeResult};
 
+/// Version of the exported recipe JSON Schemas; bump when a schema changes shape.
+pub const SCHEMA_VERSION: &str = "v1";
+
+/// `http.request` params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HttpRequestParams {
+    pub url: String,
+    #[serde(default)]
+    pub method: Option<String>,
+    #[serde(default)]
+    pub headers: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+}
+
+/// `file.read` params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileReadParams {
+    pub uri: String,
+}
+
+/// `file.write` params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileWriteParams {
+    pub uri: String,
+    pub content: String,
+}
+
+/// `file.move` params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileMoveParams {
+    pub uri: String,
+    pub destination: String,
+}
+
+/// `file.rename` params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileRenameParams {
+    pub uri: String,
+    pub new_name: String,
+}
+
+/// `notification.send` params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationSendParams {
+    pub title: String,
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+/// `clipboard.read` takes no params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ClipboardReadParams {}
+
+/// `clipboard.write` params: text or a media reference.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ClipboardWriteParams {
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub media_uri: Option<String>,
+}
+
+/// `camera.capture` and `webcam.capture` params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CaptureParams {
+    #[serde(default)]
+    pub mode: Option<String>,
+    #[serde(default)]
+    pub output_uri: Option<String>,
+}
+
+/// `microphone.record` params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MicrophoneRecordParams {
+    #[serde(default)]
+    pub max_seconds: Option<u32>,
+    #[serde(default)]
+    pub output_uri: Option<String>,
+}
+
+/// `health.read` params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HealthReadParams {
+    pub types: Vec<String>,
+    #[serde(default)]
+    pub aggregation: Option<String>,
+}
+
+/// `state.get` params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StateGetParams {
+    pub key: String,
+}
+
+/// `state.set` params.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct StateSetParams {
+    pub key: String,
+    pub value: serde_json::Value,
+}
+
+/// Output of actions that only report success.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct OkOutput {
+    pub ok: bool,
+}
+
+/// `clipboard.read` output.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct ClipboardReadOutput {
+    pub text: String,
+}
+
+/// `http.request` output.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct HttpResponseOutput {
+    pub status: u16,
+    pub body: serde_json::Value,
+}
+
+/// `camera.capture`, `webcam.capture` and `microphone.record` output: the captured media.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct MediaOutput {
+    pub kind: String,
+    pub uri: String,
+}
+
+/// `health.read` output: the day and one value per requested type.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct HealthReadOutput {
+    pub date: String,
+    #[serde(flatten)]
+    pub values: BTreeMap<String, f64>,
+}
+
+/// `state.get` output.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct StateGetOutput {
+    pub value: serde_json::Value,
+}
+
+/// Appends a serde path to a JSON pointer prefix, escaping per RFC 6901.
+fn pointer_from_path(prefix: &str, path: &serde_path_to_error::Path) -> String {
+    let mut pointer = prefix.to_string();
+    for segment in path.iter() {
+        let token = match segment {
+            serde_path_to_error::Segment::Seq { index } => index.to_string(),
+            serde_path_to_error::Segment::Map { key } => key.clone(),
+            serde_path_to_error::Segment::Enum { variant } => variant.clone(),
+            serde_path_to_error::Segment::Unknown => continue,
+        };
+        pointer.push('/');
+        pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+    }
+    pointer
+}
+
+/// Deserializes `value` strictly, reporting failures at `file` + JSON pointer.
+pub(crate) fn parse_strict<T: DeserializeOwned>(value: &serde_json::Value, file: &str, prefix: &str) -> RuntimeResult<T> {
+    serde_path_to_error::deserialize(value).map_err(|err| RuntimeError::SchemaViolation {
+        file: file.to_string(),
+        pointer: pointer_from_path(prefix, err.path()),
+        reason: err.inner().to_string(),
+    })
+}
+
+fn parse_json(bytes: &[u8], file: &str) -> RuntimeResult<serde_json::Value> {
+    serde_json::from_slice(bytes).map_err(|err| RuntimeError::Package {
+        file: file.to_string(),
+        reason: err.to_string(),
+    })
+}
+
+/// Checks `params` against the typed schema of `action_type`; unknown action types pass.
+fn validate_params(action_type: &str, params: &serde_json::Value, file: &str, pointer: &str) -> RuntimeResult<()> {
+    match action_capability(action_type) {
+        Some(capability) => capability.validate_params(params, file, pointer),
+        None => Ok(()),
+    }
+}
+
 /// Validates action parameter structure and known fields.
 pub fn validate_action_schema(action: &ActionNode) -> RuntimeResult<()> {
-    match action.action_type.as_str() {
-        "http.request" => {
-            let obj = action
-                .params
-                .as_object()
-                .ok_or_else(|| RuntimeError::SchemaValidation("http.request expects object params".to_string()))?;
-            if !obj.contains_key("url") {
-                return Err(RuntimeError::SchemaValidation("http.request missing url".to_string()));
-            }
-            Ok(())
-        }
-        "file.read" | "file.write" | "file.move" | "file.rename" => {
-            let obj = action
-                .params
-                .as_object()
-                .ok_or_else(|| RuntimeError::SchemaValidation("file action expects object params".to_string()))?;
-            if !obj.contains_key("uri") {
-                return Err(RuntimeError::SchemaValidation("file action missing uri".to_string()));
-            }
-            Ok(())
+    validate_params(&action.action_type, &action.params, "flow.json", "/params")
+}
+
+/// Validates every action's params, with pointers rooted at the flow document.
+pub fn validate_flow_params(flow: &RecipeFlow) -> RuntimeResult<()> {
+    validate_flow_params_in(flow, "flow.json")
+}
+
+/// `validate_flow_params` reporting violations against `file`, e.g. an authoring source.
+pub(crate) fn validate_flow_params_in(flow: &RecipeFlow, file: &str) -> RuntimeResult<()> {
+    for (index, action) in flow.actions.iter().enumerate() {
+        validate_params(
+            &action.action_type,
+            &action.params,
+            file,
+            &format!("/actions/{}/params", index),
+        )?;
+    }
+    Ok(())
+}
+
+/// Strictly parses `manifest.json` and `flow.json`, rejecting unknown fields.
+pub fn parse_recipe_documents(manifest: &[u8], flow: &[u8]) -> RuntimeResult<RecipeModel> {
+    let manifest: Manifest = parse_strict(&parse_json(manifest, "manifest.json")?, "manifest.json", "")?;
+    let flow: RecipeFlow = parse_strict(&parse_json(flow, "flow.json")?, "flow.json", "")?;
+    validate_flow_params(&flow)?;
+    Ok(RecipeModel { manifest, flow })
+}
+
+pub(crate) fn versioned_schema<T: JsonSchema>(name: &str) -> serde_json::Value {
+    let mut schema = schema_for!(T).to_value();
+    if let Some(obj) = schema.as_object_mut() {
+        obj.insert(
+            "$id".to_string(),
+            serde_json::Value::String(format!("urn:arquent:recipe:{}:{}", SCHEMA_VERSION, name)),
+        );
+    }
+    schema
+}
+
+pub fn manifest_json_schema() -> serde_json::Value {
+    versioned_schema::<Manifest>("manifest")
+}
+
+pub fn flow_json_schema() -> serde_json::Value {
+    versioned_schema::<RecipeFlow>("flow")
+}
+
+/// Params schema per action type, for actions with typed params.
+pub fn action_params_json_schemas() -> BTreeMap<&'static str, serde_json::Value> {
+    actions()
+        .iter()
+        .filter_map(|capability| Some((capability.action_type, capability.params_json_schema()?)))
+        .collect()
+}
+
+/// Output schema per action type, for actions with typed output.
+pub fn action_output_json_schemas() -> BTreeMap<&'static str, serde_json::Value> {
+    actions()
+        .iter()
+        .filter_map(|capability| Some((capability.action_type, capability.output_json_schema()?)))
+        .collect()
+}
+
+/// Every exported schema keyed by its file name under `<dir>/<SCHEMA_VERSION>/`.
+pub fn json_schema_files() -> RuntimeResult<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    let mut add = |name: String, schema: &serde_json::Value| -> RuntimeResult<()> {
+        let mut text =
+            serde_json::to_string_pretty(schema).map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+        text.push('\n');
+        files.insert(name, text);
+        Ok(())
+    };
+    add("manifest.schema.json".to_string(), &manifest_json_schema())?;
+    add("flow.schema.json".to_string(), &flow_json_schema())?;
+    for (action_type, schema) in action_params_json_schemas() {
+        add(format!("actions/{}.schema.json", action_type), &schema)?;
+    }
+    for (action_type, schema) in action_output_json_schemas() {
+        add(format!("actions/{}.output.schema.json", action_type), &schema)?;
+    }
+    Ok(files)
+}
+
+/// Writes the schemas for editors and the Flutter client to `<dir>/<SCHEMA_VERSION>/`.
+pub fn export_json_schemas(dir: &Path) -> RuntimeResult<()> {
+    let root = dir.join(SCHEMA_VERSION);
+    for (name, text) in json_schema_files()? {
+        let path = root.join(&name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| RuntimeError::Storage(err.to_string()))?;
         }
-        "camera.capture" | "microphone.record" | "webcam.capture" | "health.read" => Ok(()),
-        _ => Ok(()),
+        fs::write(&path, text).map_err(|err| RuntimeError::Storage(err.to_string()))?;
     }
+    Ok(())
 }
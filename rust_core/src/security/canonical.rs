@@ -0,0 +1,113 @@
+use serde_json::{Number, Value};
+
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Serializes JSON per RFC 8785 (JSON Canonicalization Scheme).
+///
+/// Object members are sorted by UTF-16 code units, strings use the minimal
+/// escaping of ECMAScript `JSON.stringify`, and numbers use the ECMAScript
+/// `Number.prototype.toString` form of their IEEE-754 double value.
+pub fn canonicalize_json(value: &Value) -> RuntimeResult<String> {
+    let mut out = String::new();
+    write_value(value, &mut out)?;
+    Ok(out)
+}
+
+/// Parses JSON bytes and returns their JCS form.
+pub fn canonicalize_json_bytes(bytes: &[u8]) -> RuntimeResult<Vec<u8>> {
+    let value: Value = serde_json::from_slice(bytes).map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+    canonicalize_json(&value).map(String::into_bytes)
+}
+
+fn write_value(value: &Value, out: &mut String) -> RuntimeResult<()> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(flag) => out.push_str(if *flag { "true" } else { "false" }),
+        Value::Number(number) => out.push_str(&format_number(number)?),
+        Value::String(text) => write_string(text, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|(left, _), (right, _)| left.encode_utf16().cmp(right.encode_utf16()));
+            out.push('{');
+            for (index, (key, item)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_value(item, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_string(text: &str, out: &mut String) {
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{0c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+fn format_number(number: &Number) -> RuntimeResult<String> {
+    let value = number
+        .as_f64()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| RuntimeError::Serialization(format!("number {} is not a finite double", number)))?;
+    Ok(format_es_number(value))
+}
+
+/// Formats a finite double like ECMAScript `Number.prototype.toString`.
+fn format_es_number(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    let sign = if value < 0.0 { "-" } else { "" };
+
+    // Rust's `{:e}` yields the shortest round-tripping digits, e.g. `1.2345e6`.
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((scientific.as_str(), "0"));
+    let digits: String = mantissa.chars().filter(|ch| *ch != '.').collect();
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+
+    let body = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat((-n) as usize), digits)
+    } else {
+        let exp_sign = if n - 1 < 0 { "-" } else { "+" };
+        let fraction = if k > 1 {
+            format!(".{}", &digits[1..])
+        } else {
+            String::new()
+        };
+        format!("{}{}e{}{}", &digits[..1], fraction, exp_sign, (n - 1).abs())
+    };
+    format!("{}{}", sign, body)
+}
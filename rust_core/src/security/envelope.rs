@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::security::canonical::canonicalize_json;
+use crate::security::keyring::key_id_for_public_key;
+use crate::security::keys::PublisherKeypair;
+use crate::security::signature::{verify_ed25519_signature, DigestVersion};
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// `format` of a structured `signature.sig`.
+pub const ENVELOPE_FORMAT: &str = "arquent.recipepkg.sig.v1";
+pub const ALGORITHM_ED25519: &str = "ed25519";
+
+/// One signer's entry; the first entry is the publisher, later ones are co-signatures.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct EnvelopeSignature {
+    pub key_id: String,
+    pub signed_at: DateTime<Utc>,
+    pub sig: String,
+}
+
+/// Structured `signature.sig`: the package digest plus the signatures over it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SignatureEnvelope {
+    pub format: String,
+    pub algorithm: String,
+    pub digest_version: String,
+    pub digest: String,
+    pub signatures: Vec<EnvelopeSignature>,
+}
+
+/// How `signature.sig` was encoded.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureFormat {
+    /// Bare base64 Ed25519 signature over the hex digest.
+    Legacy,
+    Envelope,
+}
+
+/// A verified signer; legacy signatures carry no signing time.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignerInfo {
+    pub key_id: String,
+    pub signed_at: Option<DateTime<Utc>>,
+}
+
+/// What the verifier established about a package signature.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignatureInfo {
+    pub format: SignatureFormat,
+    pub algorithm: String,
+    pub digest_version: String,
+    pub signer: SignerInfo,
+    /// Co-signatures whose keys could be resolved and verified.
+    pub cosigners: Vec<SignerInfo>,
+}
+
+impl DigestVersion {
+    /// Name recorded in envelopes; unlike `as_wire`, legacy is explicit.
+    pub fn envelope_name(&self) -> &'static str {
+        self.as_wire().unwrap_or("legacy")
+    }
+}
+
+/// Bytes each signer signs: JCS of the envelope header plus its own key id and time.
+fn signing_payload(envelope: &SignatureEnvelope, key_id: &str, signed_at: DateTime<Utc>) -> RuntimeResult<String> {
+    canonicalize_json(&serde_json::json!({
+        "format": envelope.format,
+        "algorithm": envelope.algorithm,
+        "digest_version": envelope.digest_version,
+        "digest": envelope.digest,
+        "key_id": key_id,
+        "signed_at": signed_at,
+    }))
+}
+
+impl SignatureEnvelope {
+    pub fn new(digest_version: DigestVersion, digest_hex: impl Into<String>) -> Self {
+        Self {
+            format: ENVELOPE_FORMAT.to_string(),
+            algorithm: ALGORITHM_ED25519.to_string(),
+            digest_version: digest_version.envelope_name().to_string(),
+            digest: digest_hex.into(),
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Parses `signature.sig` text; returns `None` for a legacy bare signature.
+    pub fn parse(text: &str) -> RuntimeResult<Option<Self>> {
+        let text = text.trim();
+        if !text.starts_with('{') {
+            return Ok(None);
+        }
+        let envelope: Self = serde_json::from_str(text).map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+        if envelope.format != ENVELOPE_FORMAT {
+            return Err(RuntimeError::Serialization(format!(
+                "unsupported signature format: {}",
+                envelope.format
+            )));
+        }
+        if envelope.algorithm != ALGORITHM_ED25519 {
+            return Err(RuntimeError::Crypto(format!(
+                "unsupported signature algorithm: {}",
+                envelope.algorithm
+            )));
+        }
+        if envelope.signatures.is_empty() {
+            return Err(RuntimeError::SignatureInvalid);
+        }
+        Ok(Some(envelope))
+    }
+
+    pub fn to_json_pretty(&self) -> RuntimeResult<String> {
+        let mut text =
+            serde_json::to_string_pretty(self).map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+        text.push('\n');
+        Ok(text)
+    }
+
+    /// Appends a signature; the first call signs as publisher, later calls co-sign.
+    pub fn add_signature(&mut self, keypair: &PublisherKeypair, signed_at: DateTime<Utc>) -> RuntimeResult<()> {
+        let key_id = keypair.key_id();
+        let payload = signing_payload(self, &key_id, signed_at)?;
+        self.signatures.push(EnvelopeSignature {
+            sig: keypair.sign_message(payload.as_bytes()),
+            key_id,
+            signed_at,
+        });
+        Ok(())
+    }
+
+    /// Verifies the envelope against the freshly computed package digest.
+    ///
+    /// `publisher_key_b64` must match the first signature's key id. Co-signer
+    /// keys are looked up by key id through `resolve_key`; unknown or invalid
+    /// co-signatures are left out of the result rather than failing it.
+    pub fn verify(
+        &self,
+        publisher_key_b64: &str,
+        digest_version: DigestVersion,
+        digest_hex: &str,
+        resolve_key: impl Fn(&str) -> Option<String>,
+    ) -> RuntimeResult<SignatureInfo> {
+        if self.digest_version != digest_version.envelope_name() || self.digest != digest_hex {
+            return Err(RuntimeError::SignatureInvalid);
+        }
+        let (primary, cosignatures) = self.signatures.split_first().ok_or(RuntimeError::SignatureInvalid)?;
+        if key_id_for_public_key(publisher_key_b64)? != primary.key_id {
+            return Err(RuntimeError::SignatureInvalid);
+        }
+        self.verify_entry(primary, publisher_key_b64)?;
+
+        let cosigners = cosignatures
+            .iter()
+            .filter(|entry| {
+                resolve_key(&entry.key_id)
+                    .map(|key| self.verify_entry(entry, &key).is_ok())
+                    .unwrap_or(false)
+            })
+            .map(|entry| SignerInfo {
+                key_id: entry.key_id.clone(),
+                signed_at: Some(entry.signed_at),
+            })
+            .collect();
+
+        Ok(SignatureInfo {
+            format: SignatureFormat::Envelope,
+            algorithm: self.algorithm.clone(),
+            digest_version: self.digest_version.clone(),
+            signer: SignerInfo {
+                key_id: primary.key_id.clone(),
+                signed_at: Some(primary.signed_at),
+            },
+            cosigners,
+        })
+    }
+
+    fn verify_entry(&self, entry: &EnvelopeSignature, public_key_b64: &str) -> RuntimeResult<()> {
+        if key_id_for_public_key(public_key_b64)? != entry.key_id {
+            return Err(RuntimeError::SignatureInvalid);
+        }
+        let payload = signing_payload(self, &entry.key_id, entry.signed_at)?;
+        verify_ed25519_signature(public_key_b64, &entry.sig, &payload)
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::recipe::manifest::Manifest;
+use crate::recipe::package::RecipePackage;
+use crate::security::canonical::canonicalize_json;
+use crate::security::envelope::SignatureInfo;
+use crate::security::hashing::sha256_hex;
+use crate::security::keys::PublisherKeypair;
+use crate::security::signature::verify_ed25519_signature;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+const ROTATION_DOMAIN: &str = "arquent.keyring.rotation.v1";
+
+/// Stable key id: the first 16 hex chars of sha256 over the raw public key.
+pub fn key_id_for_public_key(public_key_b64: &str) -> RuntimeResult<String> {
+    let raw = STANDARD
+        .decode(public_key_b64)
+        .map_err(|err| RuntimeError::Crypto(format!("invalid public key encoding: {}", err)))?;
+    if raw.len() != 32 {
+        return Err(RuntimeError::Crypto("Ed25519 public keys are 32 bytes".to_string()));
+    }
+    Ok(sha256_hex(&raw)[..16].to_string())
+}
+
+/// Message an outgoing key signs to endorse its successor.
+pub fn rotation_message(publisher_id: &str, old_key_id: &str, new_key_id: &str, new_public_key_b64: &str) -> String {
+    format!(
+        "{}\n{}\n{}\n{}\n{}",
+        ROTATION_DOMAIN, publisher_id, old_key_id, new_key_id, new_public_key_b64
+    )
+}
+
+/// A publisher key trusted by the runtime, valid within `[not_before, not_after)`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrustedKey {
+    pub key_id: String,
+    pub publisher_id: String,
+    pub public_key_b64: String,
+    pub not_before: DateTime<Utc>,
+    pub not_after: Option<DateTime<Utc>>,
+    /// Key id this key rotated from; `None` for a publisher's root key.
+    pub replaces: Option<String>,
+    /// Signature by the replaced key over `rotation_message`.
+    pub rotation_signature: Option<String>,
+}
+
+impl TrustedKey {
+    /// Builds a publisher root key entry, deriving the key id.
+    pub fn root(
+        publisher_id: impl Into<String>,
+        public_key_b64: impl Into<String>,
+        not_before: DateTime<Utc>,
+        not_after: Option<DateTime<Utc>>,
+    ) -> RuntimeResult<Self> {
+        let public_key_b64 = public_key_b64.into();
+        Ok(Self {
+            key_id: key_id_for_public_key(&public_key_b64)?,
+            publisher_id: publisher_id.into(),
+            public_key_b64,
+            not_before,
+            not_after,
+            replaces: None,
+            rotation_signature: None,
+        })
+    }
+
+    /// Builds the successor of `self`, endorsed with the outgoing keypair.
+    pub fn rotate_to(
+        &self,
+        outgoing: &PublisherKeypair,
+        successor_public_key_b64: impl Into<String>,
+        not_before: DateTime<Utc>,
+        not_after: Option<DateTime<Utc>>,
+    ) -> RuntimeResult<Self> {
+        let public_key_b64 = successor_public_key_b64.into();
+        let key_id = key_id_for_public_key(&public_key_b64)?;
+        let message = rotation_message(&self.publisher_id, &self.key_id, &key_id, &public_key_b64);
+        Ok(Self {
+            key_id,
+            publisher_id: self.publisher_id.clone(),
+            public_key_b64,
+            not_before,
+            not_after,
+            replaces: Some(self.key_id.clone()),
+            rotation_signature: Some(outgoing.sign_message(message.as_bytes())),
+        })
+    }
+
+    pub fn is_valid_at(&self, at: DateTime<Utc>) -> bool {
+        self.not_before <= at && self.not_after.map(|not_after| at < not_after).unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RevokedKey {
+    pub key_id: String,
+    pub revoked_at: DateTime<Utc>,
+    pub reason: String,
+}
+
+/// Revocation list signed by the keyring authority; `sequence` only moves forward.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RevocationList {
+    pub sequence: u64,
+    pub issued_at: DateTime<Utc>,
+    pub revoked: Vec<RevokedKey>,
+    pub signature: Option<String>,
+}
+
+impl RevocationList {
+    /// JCS form of the list with `signature` nulled, the bytes the authority signs.
+    pub fn signing_payload(&self) -> RuntimeResult<String> {
+        let mut unsigned = self.clone();
+        unsigned.signature = None;
+        let value = serde_json::to_value(&unsigned).map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+        canonicalize_json(&value)
+    }
+
+    pub fn sign(&mut self, authority: &PublisherKeypair) -> RuntimeResult<()> {
+        let payload = self.signing_payload()?;
+        self.signature = Some(authority.sign_message(payload.as_bytes()));
+        Ok(())
+    }
+}
+
+/// Publisher identity established by the keyring, not by the package itself.
+///
+/// Only `Keyring::verify_package` builds one, so holding it proves the check ran;
+/// it names the recipe id and version it was verified for.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct VerifiedPublisher {
+    publisher_id: String,
+    key_id: String,
+    recipe_id: String,
+    recipe_version: String,
+    signature: SignatureInfo,
+    enterprise: bool,
+}
+
+impl VerifiedPublisher {
+    pub fn publisher_id(&self) -> &str {
+        &self.publisher_id
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn signature(&self) -> &SignatureInfo {
+        &self.signature
+    }
+
+    /// Publisher is trusted by the keyring as an enterprise source.
+    pub fn is_enterprise(&self) -> bool {
+        self.enterprise
+    }
+
+    /// True when this result was produced for the package carrying `manifest`.
+    pub fn covers(&self, manifest: &Manifest) -> bool {
+        self.recipe_id == manifest.id && self.recipe_version == manifest.version
+    }
+}
+
+/// Trusted publisher keys plus the latest accepted revocation list.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Keyring {
+    /// Key that signs revocation lists.
+    pub authority_public_key_b64: String,
+    pub keys: BTreeMap<String, TrustedKey>,
+    pub revocations: RevocationList,
+    /// Publishers whose packages count as enterprise-signed, e.g. for `Restricted` recipes.
+    #[serde(default)]
+    pub enterprise_publishers: BTreeSet<String>,
+}
+
+fn untrusted(reason: impl Into<String>) -> RuntimeError {
+    RuntimeError::UntrustedPublisher(reason.into())
+}
+
+impl Keyring {
+    pub fn new(authority_public_key_b64: impl Into<String>) -> Self {
+        Self {
+            authority_public_key_b64: authority_public_key_b64.into(),
+            keys: BTreeMap::new(),
+            revocations: RevocationList {
+                sequence: 0,
+                issued_at: DateTime::<Utc>::UNIX_EPOCH,
+                revoked: Vec::new(),
+                signature: None,
+            },
+            enterprise_publishers: BTreeSet::new(),
+        }
+    }
+
+    /// Marks a publisher as an enterprise source; its keys still come from the keyring.
+    pub fn add_enterprise_publisher(&mut self, publisher_id: impl Into<String>) {
+        self.enterprise_publishers.insert(publisher_id.into());
+    }
+
+    /// Adds a publisher's root key, trusted directly by the host.
+    pub fn add_root_key(&mut self, key: TrustedKey) -> RuntimeResult<()> {
+        if key.replaces.is_some() || key.rotation_signature.is_some() {
+            return Err(untrusted("root keys must not carry rotation links"));
+        }
+        self.insert_key(key)
+    }
+
+    /// Adds a key endorsed by the key it replaces; the old key must be trusted,
+/// unrevoked and still valid at the successor's `not_before`.
+    pub fn add_rotated_key(&mut self, key: TrustedKey) -> RuntimeResult<()> {
+        let old_key_id = key
+            .replaces
+            .as_deref()
+            .ok_or_else(|| untrusted("rotated key must name the key it replaces"))?;
+        let old_key = self
+            .keys
+            .get(old_key_id)
+            .ok_or_else(|| untrusted(format!("replaced key {} is not in the keyring", old_key_id)))?;
+        if old_key.publisher_id != key.publisher_id {
+            return Err(untrusted("rotation cannot change the publisher"));
+        }
+        if self.is_revoked(old_key_id) {
+            return Err(untrusted(format!("replaced key {} is revoked", old_key_id)));
+        }
+        if !old_key.is_valid_at(key.not_before) {
+            return Err(untrusted(format!(
+                "replaced key {} is not valid at {}, when its successor takes over",
+                old_key_id,
+                key.not_before.to_rfc3339()
+            )));
+        }
+        let signature = key
+            .rotation_signature
+            .as_deref()
+            .ok_or_else(|| untrusted("rotated key is missing its rotation signature"))?;
+        let message = rotation_message(&key.publisher_id, old_key_id, &key.key_id, &key.public_key_b64);
+        verify_ed25519_signature(&old_key.public_key_b64, signature, &message)?;
+        self.insert_key(key)
+    }
+
+    fn insert_key(&mut self, key: TrustedKey) -> RuntimeResult<()> {
+        if key_id_for_public_key(&key.public_key_b64)? != key.key_id {
+            return Err(untrusted(format!("key id {} does not match its public key", key.key_id)));
+        }
+        if self.keys.contains_key(&key.key_id) {
+            return Err(untrusted(format!("key {} is already in the keyring", key.key_id)));
+        }
+        self.keys.insert(key.key_id.clone(), key);
+        Ok(())
+    }
+
+    /// Replaces the revocation list after checking the authority signature and sequence.
+    pub fn apply_revocation_list(&mut self, list: RevocationList) -> RuntimeResult<()> {
+        let signature = list.signature.as_deref().ok_or(RuntimeError::SignatureInvalid)?;
+        verify_ed25519_signature(&self.authority_public_key_b64, signature, &list.signing_payload()?)?;
+        if list.sequence <= self.revocations.sequence {
+            return Err(untrusted(format!(
+                "revocation list sequence {} is not newer than {}",
+                list.sequence, self.revocations.sequence
+            )));
+        }
+        self.revocations = list;
+        Ok(())
+    }
+
+    pub fn is_revoked(&self, key_id: &str) -> bool {
+        self.revocations.revoked.iter().any(|revoked| revoked.key_id == key_id)
+    }
+
+    /// Keys of `publisher_id` that are inside their validity window and not revoked.
+    pub fn active_keys(&self, publisher_id: &str, at: DateTime<Utc>) -> Vec<&TrustedKey> {
+        self.keys
+            .values()
+            .filter(|key| key.publisher_id == publisher_id && key.is_valid_at(at) && !self.is_revoked(&key.key_id))
+            .collect()
+    }
+
+    /// Verifies a package against the keys of the publisher its manifest names.
+    pub fn verify_package(&self, package: &RecipePackage, at: DateTime<Utc>) -> RuntimeResult<VerifiedPublisher> {
+        let publisher_id = package
+            .model
+            .manifest
+            .publisher
+            .as_ref()
+            .map(|publisher| publisher.id.as_str())
+            .ok_or_else(|| untrusted("manifest does not name a publisher"))?;
+        let keys = self.active_keys(publisher_id, at);
+        if keys.is_empty() {
+            return Err(untrusted(format!("no active trusted key for publisher {}", publisher_id)));
+        }
+        let resolve_key = |key_id: &str| {
+            self.keys
+                .get(key_id)
+                .filter(|key| key.is_valid_at(at) && !self.is_revoked(key_id))
+                .map(|key| key.public_key_b64.clone())
+        };
+        keys.into_iter()
+            .find_map(|key| {
+                package
+                    .verify_signature_with(&key.public_key_b64, resolve_key)
+                    .ok()
+                    .map(|signature| VerifiedPublisher {
+                        publisher_id: publisher_id.to_string(),
+                        key_id: key.key_id.clone(),
+                        recipe_id: package.model.manifest.id.clone(),
+                        recipe_version: package.model.manifest.version.clone(),
+                        signature,
+                        enterprise: self.enterprise_publishers.contains(publisher_id),
+                    })
+            })
+            .ok_or(RuntimeError::SignatureInvalid)
+    }
+}
@@ -0,0 +1,82 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey};
+use ed25519_dalek::{Signer, SigningKey, VerifyingKey};
+
+use crate::security::keyring::key_id_for_public_key;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Ed25519 publisher keypair used to sign recipe packages.
+///
+/// PEM output matches `recipes/tools/keygen.mjs` (PKCS#8 private, SPKI public),
+/// so keys move freely between the Node tooling and Rust.
+pub struct PublisherKeypair {
+    signing_key: SigningKey,
+}
+
+impl PublisherKeypair {
+    /// Generates a new random keypair from the OS RNG.
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        Self {
+            signing_key: SigningKey::from_bytes(&secret),
+        }
+    }
+
+    pub fn from_signing_key(signing_key: SigningKey) -> Self {
+        Self { signing_key }
+    }
+
+    /// Parses a PKCS#8 PEM private key.
+    pub fn from_pkcs8_pem(pem: &str) -> RuntimeResult<Self> {
+        SigningKey::from_pkcs8_pem(pem)
+            .map(Self::from_signing_key)
+            .map_err(|err| RuntimeError::Crypto(format!("invalid PKCS#8 private key: {}", err)))
+    }
+
+    /// Serializes the private key as PKCS#8 PEM.
+    pub fn to_pkcs8_pem(&self) -> RuntimeResult<String> {
+        self.signing_key
+            .to_pkcs8_pem(LineEnding::LF)
+            .map(|pem| pem.to_string())
+            .map_err(|err| RuntimeError::Crypto(err.to_string()))
+    }
+
+    /// Serializes the public key as SPKI PEM.
+    pub fn public_key_pem(&self) -> RuntimeResult<String> {
+        self.signing_key
+            .verifying_key()
+            .to_public_key_pem(LineEnding::LF)
+            .map_err(|err| RuntimeError::Crypto(err.to_string()))
+    }
+
+    /// Raw public key in base64, the form accepted by `verify_ed25519_signature`.
+    pub fn public_key_b64(&self) -> String {
+        STANDARD.encode(self.signing_key.verifying_key().as_bytes())
+    }
+
+    /// Key id used by `security::keyring`.
+    pub fn key_id(&self) -> String {
+        key_id_for_public_key(&self.public_key_b64()).expect("an Ed25519 verifying key is always 32 bytes")
+    }
+
+    /// Signs a hex digest string and returns the base64 signature.
+    pub fn sign_digest_hex(&self, digest_hex: &str) -> String {
+        self.sign_message(digest_hex.as_bytes())
+    }
+
+    /// Signs arbitrary bytes and returns the base64 signature.
+    pub fn sign_message(&self, message: &[u8]) -> String {
+        STANDARD.encode(self.signing_key.sign(message).to_bytes())
+    }
+}
+
+/// Converts an SPKI PEM public key (as written by `keygen.mjs`) to raw base64.
+pub fn public_key_pem_to_b64(pem: &str) -> RuntimeResult<String> {
+    VerifyingKey::from_public_key_pem(pem)
+        .map(|key| STANDARD.encode(key.as_bytes()))
+        .map_err(|err| RuntimeError::Crypto(format!("invalid SPKI public key: {}", err)))
+}
@@ -1,3 +1,7 @@
+pub mod canonical;
+pub mod envelope;
 pub mod hashing;
+pub mod keyring;
+pub mod keys;
 pub mod signature;
 pub mod validator;
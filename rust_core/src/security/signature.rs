@@ -1,7 +1,18 @@
+use std::fs;
+use std::path::Path;
+
 use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
 use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 
+use crate::recipe::assets::{AssetsManifest, ASSETS_MANIFEST_FILE};
+use crate::recipe::authoring::compile_authoring_sources;
+use crate::recipe::package::{load_source_dir, MANIFEST_FILE, SIGNATURE_FILE};
+use crate::security::canonical::{canonicalize_json, canonicalize_json_bytes};
+use crate::security::envelope::{SignatureEnvelope, SignatureFormat, SignatureInfo, SignerInfo, ALGORITHM_ED25519};
 use crate::security::hashing::sha256_hex;
+use crate::security::keyring::key_id_for_public_key;
+use crate::security::keys::PublisherKeypair;
 use crate::types::errors::{RuntimeError, RuntimeResult};
 
 /// Computes package digest sha256(manifest + flow + assets_manifest_hash).
@@ -41,6 +52,72 @@ pub fn package_digest_hex_normalized(
     ))
 }
 
+/// `manifest.digest_version` value selecting the RFC 8785 (JCS) digest.
+pub const DIGEST_VERSION_JCS_V1: &str = "jcs-v1";
+
+const JCS_V1_DOMAIN: &[u8] = b"arquent.recipepkg.digest.jcs-v1\n";
+
+/// Package digest algorithm, selected by `manifest.digest_version`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestVersion {
+    /// `package_digest_hex_normalized`: serializer-dependent manifest, raw flow bytes.
+    Legacy,
+    /// JCS-canonical manifest and flow, so key order, whitespace and number
+    /// formatting no longer affect the digest.
+    JcsV1,
+}
+
+impl DigestVersion {
+    /// Reads the digest version marker; manifests without one use `Legacy`.
+    pub fn from_manifest(manifest: &[u8]) -> RuntimeResult<Self> {
+        let manifest_json: serde_json::Value = serde_json::from_slice(manifest)
+            .map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+        match manifest_json.get("digest_version") {
+            None | Some(serde_json::Value::Null) => Ok(Self::Legacy),
+            Some(serde_json::Value::String(marker)) if marker == DIGEST_VERSION_JCS_V1 => Ok(Self::JcsV1),
+            Some(other) => Err(RuntimeError::Serialization(format!(
+                "unsupported digest_version: {}",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_wire(&self) -> Option<&'static str> {
+        match self {
+            Self::Legacy => None,
+            Self::JcsV1 => Some(DIGEST_VERSION_JCS_V1),
+        }
+    }
+}
+
+/// Computes the JCS package digest: sha256(domain + JCS(manifest) + JCS(flow) + assets_manifest_hash).
+pub fn package_digest_hex_jcs(manifest: &[u8], flow: &[u8], assets_manifest_hash: &str) -> RuntimeResult<String> {
+    let mut manifest_json: serde_json::Value = serde_json::from_slice(manifest)
+        .map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+    manifest_json
+        .as_object_mut()
+        .ok_or_else(|| RuntimeError::Serialization("manifest must be a JSON object".to_string()))?
+        .insert("signature".to_string(), serde_json::Value::Null);
+
+    let mut payload = JCS_V1_DOMAIN.to_vec();
+    payload.extend_from_slice(canonicalize_json(&manifest_json)?.as_bytes());
+    payload.extend_from_slice(&canonicalize_json_bytes(flow)?);
+    payload.extend_from_slice(assets_manifest_hash.as_bytes());
+    Ok(sha256_hex(&payload))
+}
+
+/// Computes the package digest with the algorithm the manifest declares.
+pub fn package_digest_hex_versioned(
+    manifest: &[u8],
+    flow: &[u8],
+    assets_manifest_hash: &str,
+) -> RuntimeResult<String> {
+    match DigestVersion::from_manifest(manifest)? {
+        DigestVersion::Legacy => package_digest_hex_normalized(manifest, flow, assets_manifest_hash),
+        DigestVersion::JcsV1 => package_digest_hex_jcs(manifest, flow, assets_manifest_hash),
+    }
+}
+
 /// Verifies Ed25519 signature against digest bytes.
 pub fn verify_ed25519_signature(
     public_key_b64: &str,
@@ -68,7 +145,7 @@ pub fn verify_ed25519_signature(
         .map_err(|_| RuntimeError::SignatureInvalid)
 }
 
-/// Verifies a package signature against normalized package digest.
+/// Verifies a package signature against the digest version the manifest declares.
 pub fn verify_recipe_package_signature(
     public_key_b64: &str,
     signature_b64: &str,
@@ -76,6 +153,163 @@ pub fn verify_recipe_package_signature(
     flow: &[u8],
     assets_manifest_hash: &str,
 ) -> RuntimeResult<()> {
-    let digest_hex = package_digest_hex_normalized(manifest, flow, assets_manifest_hash)?;
+    let digest_hex = package_digest_hex_versioned(manifest, flow, assets_manifest_hash)?;
     verify_ed25519_signature(public_key_b64, signature_b64, &digest_hex)
 }
+
+/// Verifies `signature.sig` text, either a legacy bare signature or an envelope.
+///
+/// `resolve_key` maps co-signer key ids to public keys; see `SignatureEnvelope::verify`.
+pub fn verify_package_signature_file(
+    public_key_b64: &str,
+    signature_text: &str,
+    manifest: &[u8],
+    flow: &[u8],
+    assets_manifest_hash: &str,
+    resolve_key: impl Fn(&str) -> Option<String>,
+) -> RuntimeResult<SignatureInfo> {
+    let digest_version = DigestVersion::from_manifest(manifest)?;
+    match SignatureEnvelope::parse(signature_text)? {
+        Some(envelope) => {
+            let digest_hex = package_digest_hex_versioned(manifest, flow, assets_manifest_hash)?;
+            envelope.verify(public_key_b64, digest_version, &digest_hex, resolve_key)
+        }
+        None => {
+            verify_recipe_package_signature(public_key_b64, signature_text.trim(), manifest, flow, assets_manifest_hash)?;
+            Ok(SignatureInfo {
+                format: SignatureFormat::Legacy,
+                algorithm: ALGORITHM_ED25519.to_string(),
+                digest_version: digest_version.envelope_name().to_string(),
+                signer: SignerInfo {
+                    key_id: key_id_for_public_key(public_key_b64)?,
+                    signed_at: None,
+                },
+                cosigners: Vec::new(),
+            })
+        }
+    }
+}
+
+fn write_package_file(package_dir: &Path, name: &str, contents: &str) -> RuntimeResult<()> {
+    fs::write(package_dir.join(name), contents).map_err(|err| RuntimeError::Package {
+        file: name.to_string(),
+        reason: err.to_string(),
+    })
+}
+
+/// Marks the manifest with `digest_version` and returns it with the digest to sign.
+fn prepare_package_digest(
+    package_dir: &Path,
+    digest_version: DigestVersion,
+) -> RuntimeResult<(serde_json::Value, String)> {
+    if !package_dir.is_dir() {
+        return Err(RuntimeError::Package {
+            file: package_dir.display().to_string(),
+            reason: "only package directories can be signed in place".to_string(),
+        });
+    }
+    compile_authoring_sources(package_dir)?;
+    let package = load_source_dir(package_dir)?;
+    let mut manifest_json: serde_json::Value = serde_json::from_slice(&package.manifest_bytes)
+        .map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+    let manifest_obj = manifest_json
+        .as_object_mut()
+        .ok_or_else(|| RuntimeError::Serialization("manifest must be a JSON object".to_string()))?;
+    match digest_version.as_wire() {
+        Some(marker) => manifest_obj.insert("digest_version".to_string(), serde_json::Value::String(marker.to_string())),
+        None => manifest_obj.remove("digest_version"),
+    };
+
+    let marked_manifest =
+        serde_json::to_vec(&manifest_json).map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+    let digest_hex = match digest_version {
+        DigestVersion::Legacy => {
+            package_digest_hex_normalized(&marked_manifest, &package.flow_bytes, &package.assets_manifest_hash)?
+        }
+        DigestVersion::JcsV1 => {
+            let assets_manifest = AssetsManifest::compute(&package.assets);
+            write_package_file(package_dir, ASSETS_MANIFEST_FILE, &assets_manifest.to_json_pretty()?)?;
+            package_digest_hex_jcs(&marked_manifest, &package.flow_bytes, &assets_manifest.hash()?)?
+        }
+    };
+    Ok((manifest_json, digest_hex))
+}
+
+/// Writes `manifest.signature` and `signature.sig`.
+fn write_signed_package(
+    package_dir: &Path,
+    mut manifest_json: serde_json::Value,
+    signature_b64: &str,
+    signature_file: &str,
+) -> RuntimeResult<()> {
+    if let Some(manifest_obj) = manifest_json.as_object_mut() {
+        manifest_obj.insert("signature".to_string(), serde_json::Value::String(signature_b64.to_string()));
+    }
+    let mut manifest_text = serde_json::to_string_pretty(&manifest_json)
+        .map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+    manifest_text.push('\n');
+    write_package_file(package_dir, MANIFEST_FILE, &manifest_text)?;
+    write_package_file(package_dir, SIGNATURE_FILE, signature_file)
+}
+
+/// Signs a package directory in place and returns the base64 signature.
+///
+/// Writes a legacy bare `signature.sig` and sets `manifest.signature` (and
+/// `digest_version`), mirroring the Node signing tool. `jcs-v1` also
+/// (re)writes `assets.json`. Both digest versions null the signature field,
+/// so the digest does not change when the signature is written back.
+pub fn sign_recipe_package(
+    package_dir: &Path,
+    keypair: &PublisherKeypair,
+    digest_version: DigestVersion,
+) -> RuntimeResult<String> {
+    let (manifest_json, digest_hex) = prepare_package_digest(package_dir, digest_version)?;
+    let signature_b64 = keypair.sign_digest_hex(&digest_hex);
+    write_signed_package(package_dir, manifest_json, &signature_b64, &signature_b64)?;
+    Ok(signature_b64)
+}
+
+/// Signs a package directory in place with a structured signature envelope.
+///
+/// `manifest.signature` holds the publisher's envelope signature so tooling
+/// that only inspects the manifest still sees the package as signed.
+pub fn sign_recipe_package_envelope(
+    package_dir: &Path,
+    keypair: &PublisherKeypair,
+    digest_version: DigestVersion,
+    signed_at: DateTime<Utc>,
+) -> RuntimeResult<SignatureEnvelope> {
+    let (manifest_json, digest_hex) = prepare_package_digest(package_dir, digest_version)?;
+    let mut envelope = SignatureEnvelope::new(digest_version, digest_hex);
+    envelope.add_signature(keypair, signed_at)?;
+    let primary = envelope.signatures[0].sig.clone();
+    write_signed_package(package_dir, manifest_json, &primary, &envelope.to_json_pretty()?)?;
+    Ok(envelope)
+}
+
+/// Adds a co-signature to a package already signed with an envelope.
+pub fn cosign_recipe_package(
+    package_dir: &Path,
+    keypair: &PublisherKeypair,
+    signed_at: DateTime<Utc>,
+) -> RuntimeResult<SignatureEnvelope> {
+    let package = load_source_dir(package_dir)?;
+    let mut envelope = package
+        .signature
+        .as_deref()
+        .map(SignatureEnvelope::parse)
+        .transpose()?
+        .flatten()
+        .ok_or_else(|| RuntimeError::Package {
+            file: SIGNATURE_FILE.to_string(),
+            reason: "co-signing requires a signature envelope".to_string(),
+        })?;
+    let digest_hex =
+        package_digest_hex_versioned(&package.manifest_bytes, &package.flow_bytes, &package.assets_manifest_hash)?;
+    if envelope.digest != digest_hex {
+        return Err(RuntimeError::SignatureInvalid);
+    }
+    envelope.add_signature(keypair, signed_at)?;
+    write_package_file(package_dir, SIGNATURE_FILE, &envelope.to_json_pretty()?)?;
+    Ok(envelope)
+}
@@ -1,20 +1,57 @@
 use crate::engine::risk::RiskLevel;
 use crate::recipe::manifest::Manifest;
+use crate::security::keyring::VerifiedPublisher;
 use crate::types::errors::{RuntimeError, RuntimeResult};
 
+/// True when the keyring verified the manifest's declared publisher for this recipe version.
+fn is_verified_publisher(manifest: &Manifest, verified_publisher: Option<&VerifiedPublisher>) -> bool {
+    match (manifest.publisher.as_ref(), verified_publisher) {
+        (Some(declared), Some(verified)) => declared.id == verified.publisher_id() && verified.covers(manifest),
+        _ => false,
+    }
+}
+
+/// Requires `Restricted` recipes to come from a keyring-verified enterprise publisher.
+pub fn validate_restricted_source(
+    manifest: &Manifest,
+    verified_publisher: Option<&VerifiedPublisher>,
+) -> RuntimeResult<()> {
+    if manifest.risk_level != RiskLevel::Restricted {
+        return Ok(());
+    }
+    let enterprise = verified_publisher.is_some_and(VerifiedPublisher::is_enterprise);
+    if !enterprise || !is_verified_publisher(manifest, verified_publisher) {
+        return Err(RuntimeError::PermissionDenied {
+            reason: "restricted recipes must be signed by an enterprise publisher".to_string(),
+            code: "ENTERPRISE_SIGNATURE_REQUIRED".to_string(),
+        });
+    }
+    Ok(())
+}
+
 /// Marketplace validation for publish workflow.
-pub fn validate_publish_policy(manifest: &Manifest, public_marketplace: bool) -> RuntimeResult<()> {
+///
+/// `verified_publisher` is the result of `Keyring::verify_package`; the
+/// manifest's own `publisher.verified` flag is ignored.
+pub fn validate_publish_policy(
+    manifest: &Manifest,
+    verified_publisher: Option<&VerifiedPublisher>,
+    public_marketplace: bool,
+) -> RuntimeResult<()> {
     if manifest.signature.is_none() {
         return Err(RuntimeError::SignatureInvalid);
     }
 
+    if public_marketplace && manifest.risk_level == RiskLevel::Restricted {
+        return Err(RuntimeError::PermissionDenied {
+            reason: "restricted recipes cannot be published to the public marketplace".to_string(),
+            code: "RESTRICTED_NOT_PUBLIC".to_string(),
+        });
+    }
+    validate_restricted_source(manifest, verified_publisher)?;
+
     if public_marketplace && manifest.risk_level == RiskLevel::Sensitive {
-        let verified = manifest
-            .publisher
-            .as_ref()
-            .map(|publisher| publisher.verified)
-            .unwrap_or(false);
-        if !verified {
+        if !is_verified_publisher(manifest, verified_publisher) {
             return Err(RuntimeError::PermissionDenied {
                 reason: "sensitive recipes require verified publisher".to_string(),
                 code: "VERIFIED_PUBLISHER_REQUIRED".to_string(),
@@ -0,0 +1,286 @@
+use chrono::Utc;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::security::hashing::sha256_hex;
+use crate::security::keyring::VerifiedPublisher;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// `prev_hash` of the first entry in the chain.
+pub const AUDIT_GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Kinds of evidence recorded in the audit chain.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AuditEvent {
+    CapabilityAccess,
+    RuntimeProofConsumed,
+    /// Package signature accepted at install; the entry carries an `AuditSigner`.
+    SignatureVerified,
+}
+
+impl AuditEvent {
+    fn as_wire(&self) -> &'static str {
+        match self {
+            Self::CapabilityAccess => "capability_access",
+            Self::RuntimeProofConsumed => "runtime_proof_consumed",
+            Self::SignatureVerified => "signature_verified",
+        }
+    }
+
+    fn from_wire(value: &str) -> RuntimeResult<Self> {
+        match value {
+            "capability_access" => Ok(Self::CapabilityAccess),
+            "runtime_proof_consumed" => Ok(Self::RuntimeProofConsumed),
+            "signature_verified" => Ok(Self::SignatureVerified),
+            _ => Err(RuntimeError::Serialization(format!("unknown audit event: {}", value))),
+        }
+    }
+}
+
+/// Keyring-verified signer of an installed package.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuditSigner {
+    pub publisher_id: String,
+    pub key_id: String,
+    pub digest_version: String,
+    pub signed_at: Option<String>,
+}
+
+/// One hash-chained audit entry.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub seq: i64,
+    pub recipe_id: String,
+    pub run_id: String,
+    pub event: AuditEvent,
+    pub capability: String,
+    pub recorded_at: String,
+    pub prev_hash: String,
+    pub entry_hash: String,
+    /// Set for `SignatureVerified` entries only.
+    pub signer: Option<AuditSigner>,
+}
+
+impl AuditEntry {
+    /// Hashes every field except `entry_hash`, chaining on `prev_hash`.
+    ///
+    /// The signer is appended only when present, so entries written before
+    /// signer columns existed keep their hash.
+    pub fn compute_hash(&self) -> String {
+        let mut payload = vec![
+            serde_json::json!(self.seq),
+            serde_json::json!(self.recipe_id),
+            serde_json::json!(self.run_id),
+            serde_json::json!(self.event.as_wire()),
+            serde_json::json!(self.capability),
+            serde_json::json!(self.recorded_at),
+            serde_json::json!(self.prev_hash),
+        ];
+        if let Some(signer) = &self.signer {
+            payload.extend([
+                serde_json::json!(signer.publisher_id),
+                serde_json::json!(signer.key_id),
+                serde_json::json!(signer.digest_version),
+                serde_json::json!(signer.signed_at),
+            ]);
+        }
+        sha256_hex(serde_json::Value::Array(payload).to_string().as_bytes())
+    }
+}
+
+/// Last verified entry of the chain, which hosts may anchor outside SQLite.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuditHead {
+    pub seq: i64,
+    pub entry_hash: String,
+}
+
+fn storage_err(err: rusqlite::Error) -> RuntimeError {
+    RuntimeError::Storage(err.to_string())
+}
+
+/// Appends an entry linked to the current chain head.
+pub fn append_audit_entry(
+    conn: &Connection,
+    recipe_id: &str,
+    run_id: &str,
+    event: AuditEvent,
+    capability: &str,
+) -> RuntimeResult<AuditEntry> {
+    append_entry(conn, recipe_id, run_id, event, capability, None)
+}
+
+/// Records who signed an installed package and when, under run id `install`.
+///
+/// `storage::recipes::install_recipe` calls this inside its transaction for
+/// every package installed with a keyring-verified publisher.
+pub fn append_signature_verification(
+    conn: &Connection,
+    recipe_id: &str,
+    publisher: &VerifiedPublisher,
+) -> RuntimeResult<AuditEntry> {
+    let signer = AuditSigner {
+        publisher_id: publisher.publisher_id().to_string(),
+        key_id: publisher.key_id().to_string(),
+        digest_version: publisher.signature().digest_version.clone(),
+        signed_at: publisher.signature().signer.signed_at.map(|signed_at| signed_at.to_rfc3339()),
+    };
+    append_entry(
+        conn,
+        recipe_id,
+        "install",
+        AuditEvent::SignatureVerified,
+        "package_signature",
+        Some(signer),
+    )
+}
+
+/// Inserts the entry in its own transaction, or in the caller's when one is open.
+fn append_entry(
+    conn: &Connection,
+    recipe_id: &str,
+    run_id: &str,
+    event: AuditEvent,
+    capability: &str,
+    signer: Option<AuditSigner>,
+) -> RuntimeResult<AuditEntry> {
+    if !conn.is_autocommit() {
+        return insert_entry(conn, recipe_id, run_id, event, capability, signer);
+    }
+    let tx = conn.unchecked_transaction().map_err(storage_err)?;
+    let entry = insert_entry(&tx, recipe_id, run_id, event, capability, signer)?;
+    tx.commit().map_err(storage_err)?;
+    Ok(entry)
+}
+
+fn insert_entry(
+    conn: &Connection,
+    recipe_id: &str,
+    run_id: &str,
+    event: AuditEvent,
+    capability: &str,
+    signer: Option<AuditSigner>,
+) -> RuntimeResult<AuditEntry> {
+    let head: Option<(i64, String)> = conn
+        .query_row(
+            "SELECT seq, entry_hash FROM audit_log ORDER BY seq DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(storage_err)?;
+    let (seq, prev_hash) = match head {
+        Some((seq, hash)) => (seq + 1, hash),
+        None => (1, AUDIT_GENESIS_HASH.to_string()),
+    };
+
+    let mut entry = AuditEntry {
+        seq,
+        recipe_id: recipe_id.to_string(),
+        run_id: run_id.to_string(),
+        event,
+        capability: capability.to_string(),
+        recorded_at: Utc::now().to_rfc3339(),
+        prev_hash,
+        entry_hash: String::new(),
+        signer,
+    };
+    entry.entry_hash = entry.compute_hash();
+
+    let signer = entry.signer.as_ref();
+    conn.execute(
+        "INSERT INTO audit_log (seq, recipe_id, run_id, event, capability, recorded_at, prev_hash, entry_hash,
+           signer_publisher_id, signer_key_id, signer_digest_version, signer_signed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            entry.seq,
+            entry.recipe_id,
+            entry.run_id,
+            entry.event.as_wire(),
+            entry.capability,
+            entry.recorded_at,
+            entry.prev_hash,
+            entry.entry_hash,
+            signer.map(|signer| &signer.publisher_id),
+            signer.map(|signer| &signer.key_id),
+            signer.map(|signer| &signer.digest_version),
+            signer.and_then(|signer| signer.signed_at.as_ref())
+        ],
+    )
+    .map_err(storage_err)?;
+    Ok(entry)
+}
+
+fn entry_from_row(row: &Row<'_>) -> rusqlite::Result<AuditEntry> {
+    let event: String = row.get(3)?;
+    Ok(AuditEntry {
+        seq: row.get(0)?,
+        recipe_id: row.get(1)?,
+        run_id: row.get(2)?,
+        event: AuditEvent::from_wire(&event)
+            .map_err(|err| rusqlite::Error::FromSqlConversionFailure(3, Type::Text, Box::new(err)))?,
+        capability: row.get(4)?,
+        recorded_at: row.get(5)?,
+        prev_hash: row.get(6)?,
+        entry_hash: row.get(7)?,
+        signer: match (row.get::<_, Option<String>>(8)?, row.get::<_, Option<String>>(9)?) {
+            (Some(publisher_id), Some(key_id)) => Some(AuditSigner {
+                publisher_id,
+                key_id,
+                digest_version: row.get::<_, Option<String>>(10)?.unwrap_or_default(),
+                signed_at: row.get(11)?,
+            }),
+            _ => None,
+        },
+    })
+}
+
+/// Lists audit entries in chain order.
+pub fn list_audit_entries(conn: &Connection) -> RuntimeResult<Vec<AuditEntry>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT seq, recipe_id, run_id, event, capability, recorded_at, prev_hash, entry_hash,
+               signer_publisher_id, signer_key_id, signer_digest_version, signer_signed_at
+             FROM audit_log ORDER BY seq",
+        )
+        .map_err(storage_err)?;
+    let rows = stmt.query_map([], entry_from_row).map_err(storage_err)?;
+    rows.collect::<Result<_, _>>().map_err(storage_err)
+}
+
+/// Walks the chain and fails on the first gap, broken link or edited entry.
+///
+/// Returns the verified head so hosts can compare it with an externally
+/// anchored copy, which also detects truncation of the newest entries.
+pub fn verify_audit_chain(conn: &Connection) -> RuntimeResult<Option<AuditHead>> {
+    let mut expected_prev = AUDIT_GENESIS_HASH.to_string();
+    let mut head = None;
+
+    for (expected_seq, entry) in (1..).zip(list_audit_entries(conn)?) {
+        if entry.seq != expected_seq {
+            return Err(RuntimeError::AuditChainBroken {
+                seq: entry.seq,
+                reason: format!("expected entry {}, entries are missing", expected_seq),
+            });
+        }
+        if entry.prev_hash != expected_prev {
+            return Err(RuntimeError::AuditChainBroken {
+                seq: entry.seq,
+                reason: "prev_hash does not match previous entry".to_string(),
+            });
+        }
+        if entry.compute_hash() != entry.entry_hash {
+            return Err(RuntimeError::AuditChainBroken {
+                seq: entry.seq,
+                reason: "entry content does not match entry_hash".to_string(),
+            });
+        }
+        expected_prev = entry.entry_hash.clone();
+        head = Some(AuditHead {
+            seq: entry.seq,
+            entry_hash: entry.entry_hash,
+        });
+    }
+    Ok(head)
+}
@@ -0,0 +1,495 @@
+use std::collections::{BTreeMap, HashSet};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::aead::{AeadCore, OsRng};
+use chacha20poly1305::XChaCha20Poly1305;
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::connectors::registry::ConnectorRegistry;
+use crate::engine::grants::PermissionGrant;
+use crate::engine::logging::ExecutionLog;
+use crate::engine::policy::PolicySettings;
+use crate::recipe::catalog::is_sensitive_permission;
+use crate::recipe::flow::RecipeFlow;
+use crate::recipe::install::{validate_install, InstallContext};
+use crate::recipe::manifest::Manifest;
+use crate::recipe::model::RecipeModel;
+use crate::storage::config::{list_stored_config, put_stored_config};
+use crate::storage::crypto::{decrypt, encrypt, KeyEncryptionKey};
+use crate::storage::grants::list_all_grants;
+use crate::storage::migrations::SCHEMA_VERSION;
+use crate::storage::policy::{load_policy_settings, write_policy_settings};
+use crate::storage::records::{
+    append_execution_log, list_all_execution_logs, list_all_media_metadata, list_all_state, put_media_metadata,
+    put_state, run_recorded,
+};
+use crate::types::context::DeviceMeta;
+use crate::types::datavalue::{DataValue, MediaRef};
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Archive format marker written into every backup header.
+pub const BACKUP_FORMAT: &str = "arquent-backup";
+
+/// `changed_by` recorded in policy history when a backup replaces local policy.
+pub const BACKUP_IMPORT_ACTOR: &str = "backup-import";
+
+/// Largest Argon2id memory cost accepted from an archive header, in KiB.
+pub const MAX_KDF_MEMORY_KIB: u32 = 4 * Params::DEFAULT_M_COST;
+/// Largest Argon2id pass count accepted from an archive header.
+pub const MAX_KDF_ITERATIONS: u32 = 4 * Params::DEFAULT_T_COST;
+/// Largest Argon2id lane count accepted from an archive header.
+pub const MAX_KDF_PARALLELISM: u32 = 4 * Params::DEFAULT_P_COST;
+
+/// Argon2id parameters used to derive the archive key from the passphrase.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupKdf {
+    pub algorithm: String,
+    pub salt: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for BackupKdf {
+    fn default() -> Self {
+        Self {
+            algorithm: "argon2id".to_string(),
+            salt: STANDARD.encode(XChaCha20Poly1305::generate_nonce(&mut OsRng)),
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// Unencrypted archive header. It is bound into the AEAD tag as associated data.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupHeader {
+    pub format: String,
+    pub schema_version: u32,
+    pub created_at: String,
+    pub kdf: BackupKdf,
+    pub nonce: String,
+}
+
+/// Encrypted backup archive as written to disk.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupArchive {
+    pub header: BackupHeader,
+    pub ciphertext: String,
+}
+
+/// Installed recipe row as stored in the `recipes` table.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupRecipe {
+    pub id: String,
+    pub manifest: String,
+    pub flow: String,
+    pub enabled: bool,
+    pub scope: String,
+}
+
+/// One decrypted `state_kv` entry.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupStateEntry {
+    pub recipe_id: String,
+    pub key: String,
+    pub value: DataValue,
+}
+
+/// User config values stored for one recipe in `recipe_config`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupConfig {
+    pub recipe_id: String,
+    pub values: BTreeMap<String, serde_json::Value>,
+}
+
+/// One decrypted `captured_media` metadata row. The media files stay on the device.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupMedia {
+    pub recipe_id: String,
+    pub run_id: String,
+    pub media: MediaRef,
+}
+
+/// Decrypted archive contents.
+///
+/// `audit_log` is deliberately not exported: its hash chain is evidence of
+/// what happened on this device, and replaying it elsewhere would either break
+/// the chain or require re-hashing it, which would make it worthless.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupPayload {
+    pub recipes: Vec<BackupRecipe>,
+    pub config: Vec<BackupConfig>,
+    pub state: Vec<BackupStateEntry>,
+    pub grants: Vec<PermissionGrant>,
+    pub policy: PolicySettings,
+    pub logs: Option<Vec<ExecutionLog>>,
+    /// Exported together with logs, since both describe past runs.
+    pub media: Option<Vec<BackupMedia>>,
+}
+
+/// How imported rows combine with the local database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Upserts archive rows and keeps local rows and policy that the archive lacks.
+    Merge,
+    /// Clears recipes, config, state, grants, logs and media metadata, then
+    /// restores the archive. Archive policy replaces local policy only when it
+    /// is at least as strict, so a backup cannot loosen a managed device.
+    Replace,
+}
+
+/// Archive recipe that failed install validation and was not restored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedRecipe {
+    pub id: String,
+    pub reason: String,
+}
+
+/// Outcome of an import, including grants the user has to consent to again.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub recipes_restored: usize,
+    pub config_restored: usize,
+    pub state_restored: usize,
+    pub grants_restored: usize,
+    /// Logs added; runs already logged locally are skipped, so re-imports are no-ops.
+    pub logs_restored: usize,
+    pub media_restored: usize,
+    pub policy_restored: bool,
+    /// Archive policy was looser than local policy and was not applied.
+    pub policy_skipped: bool,
+    pub pending_consent: Vec<PermissionGrant>,
+    /// Recipes skipped together with their config, state and grants.
+    pub recipes_rejected: Vec<RejectedRecipe>,
+}
+
+fn storage_err(err: rusqlite::Error) -> RuntimeError {
+    RuntimeError::Storage(err.to_string())
+}
+
+fn derive_archive_key(passphrase: &str, kdf: &BackupKdf) -> RuntimeResult<[u8; 32]> {
+    if passphrase.is_empty() {
+        return Err(RuntimeError::SchemaValidation("backup passphrase is required".to_string()));
+    }
+    if kdf.algorithm != "argon2id" {
+        return Err(RuntimeError::Crypto(format!("unsupported backup kdf: {}", kdf.algorithm)));
+    }
+    // The header is only authenticated after the key is derived, so its cost
+    // parameters are bounded first.
+    if kdf.memory_kib > MAX_KDF_MEMORY_KIB
+        || kdf.iterations > MAX_KDF_ITERATIONS
+        || kdf.parallelism > MAX_KDF_PARALLELISM
+    {
+        return Err(RuntimeError::Crypto(format!(
+            "backup kdf parameters exceed limits (memory {} KiB, {} iterations, {} lanes)",
+            kdf.memory_kib, kdf.iterations, kdf.parallelism
+        )));
+    }
+    let salt = STANDARD
+        .decode(&kdf.salt)
+        .map_err(|_| RuntimeError::Crypto("backup salt is not base64".to_string()))?;
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|err| RuntimeError::Crypto(err.to_string()))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|err| RuntimeError::Crypto(err.to_string()))?;
+    Ok(key)
+}
+
+/// Runs an archive recipe row through the checks `install_recipe` applies.
+///
+/// Archives carry no package signature, so `context` has no verified publisher
+/// and Restricted recipes are always rejected; they must be reinstalled from
+/// their enterprise catalog.
+fn validate_restored_recipe(
+    recipe: &BackupRecipe,
+    context: &InstallContext,
+    registry: &ConnectorRegistry,
+) -> RuntimeResult<()> {
+    let manifest: Manifest =
+        serde_json::from_str(&recipe.manifest).map_err(|err| RuntimeError::SchemaValidation(err.to_string()))?;
+    let flow: RecipeFlow =
+        serde_json::from_str(&recipe.flow).map_err(|err| RuntimeError::SchemaValidation(err.to_string()))?;
+    if manifest.id != recipe.id {
+        return Err(RuntimeError::SchemaValidation(format!(
+            "recipe row {} holds the manifest of {}",
+            recipe.id, manifest.id
+        )));
+    }
+    validate_install(&RecipeModel { manifest, flow }, context, registry)
+}
+
+fn header_aad(header: &BackupHeader) -> RuntimeResult<Vec<u8>> {
+    let aad_header = BackupHeader {
+        nonce: String::new(),
+        ..header.clone()
+    };
+    serde_json::to_vec(&aad_header).map_err(|err| RuntimeError::Serialization(err.to_string()))
+}
+
+fn list_recipes(conn: &Connection) -> RuntimeResult<Vec<BackupRecipe>> {
+    let mut stmt = conn
+        .prepare("SELECT id, manifest, flow, enabled, scope FROM recipes ORDER BY id")
+        .map_err(storage_err)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(BackupRecipe {
+                id: row.get(0)?,
+                manifest: row.get(1)?,
+                flow: row.get(2)?,
+                enabled: row.get(3)?,
+                scope: row.get(4)?,
+            })
+        })
+        .map_err(storage_err)?;
+    rows.collect::<Result<_, _>>().map_err(storage_err)
+}
+
+/// Exports recipes, config, state, grants, policy and optionally logs and
+/// captured-media metadata as an encrypted archive.
+///
+/// State, logs and media metadata are decrypted with the device KEK and
+/// re-sealed under a key derived from `passphrase`, so the archive can be
+/// restored on another device. The audit log stays local (see `BackupPayload`).
+pub fn export_backup(
+    conn: &Connection,
+    kek: &KeyEncryptionKey,
+    passphrase: &str,
+    include_logs: bool,
+) -> RuntimeResult<Vec<u8>> {
+    let payload = BackupPayload {
+        recipes: list_recipes(conn)?,
+        config: list_stored_config(conn)?
+            .into_iter()
+            .map(|(recipe_id, values)| BackupConfig { recipe_id, values })
+            .collect(),
+        grants: list_all_grants(conn)?,
+        state: list_all_state(conn, kek)?
+            .into_iter()
+            .map(|(recipe_id, key, value)| BackupStateEntry { recipe_id, key, value })
+            .collect(),
+        policy: load_policy_settings(conn)?,
+        logs: if include_logs {
+            Some(list_all_execution_logs(conn, kek)?)
+        } else {
+            None
+        },
+        media: if include_logs {
+            Some(
+                list_all_media_metadata(conn, kek)?
+                    .into_iter()
+                    .map(|(recipe_id, run_id, media)| BackupMedia { recipe_id, run_id, media })
+                    .collect(),
+            )
+        } else {
+            None
+        },
+    };
+
+    let mut header = BackupHeader {
+        format: BACKUP_FORMAT.to_string(),
+        schema_version: SCHEMA_VERSION,
+        created_at: Utc::now().to_rfc3339(),
+        kdf: BackupKdf::default(),
+        nonce: String::new(),
+    };
+    let key = derive_archive_key(passphrase, &header.kdf)?;
+    let plaintext = serde_json::to_vec(&payload).map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+    let (nonce, ciphertext) = encrypt(&key, &header_aad(&header)?, &plaintext)?;
+    header.nonce = nonce;
+
+    serde_json::to_vec_pretty(&BackupArchive { header, ciphertext })
+        .map_err(|err| RuntimeError::Serialization(err.to_string()))
+}
+
+/// Upgrades a decrypted payload from an older schema version to `SCHEMA_VERSION`.
+///
+/// Each step rewrites the JSON for one version bump; version 1 is the first format.
+pub(crate) fn migrate_payload(mut payload: serde_json::Value, from_version: u32) -> RuntimeResult<serde_json::Value> {
+    if from_version == 0 || from_version > SCHEMA_VERSION {
+        return Err(RuntimeError::SchemaValidation(format!(
+            "backup schema version {} is not supported (current {})",
+            from_version, SCHEMA_VERSION
+        )));
+    }
+    let Some(fields) = payload.as_object_mut() else {
+        return Err(RuntimeError::SchemaValidation("backup payload is not an object".to_string()));
+    };
+    if from_version < 2 {
+        // Version 2 added recipe config and captured-media metadata. Version-1
+        // archives never held them, so media is absent exactly when logs are.
+        fields.insert("config".to_string(), serde_json::json!([]));
+        let media = if fields.get("logs").is_some_and(|logs| !logs.is_null()) {
+            serde_json::json!([])
+        } else {
+            serde_json::Value::Null
+        };
+        fields.insert("media".to_string(), media);
+    }
+    Ok(payload)
+}
+
+/// Decrypts, authenticates and migrates an archive without touching the database.
+pub fn read_backup(archive: &[u8], passphrase: &str) -> RuntimeResult<(BackupHeader, BackupPayload)> {
+    let archive: BackupArchive =
+        serde_json::from_slice(archive).map_err(|err| RuntimeError::SchemaValidation(err.to_string()))?;
+    if archive.header.format != BACKUP_FORMAT {
+        return Err(RuntimeError::SchemaValidation(format!(
+            "unknown backup format: {}",
+            archive.header.format
+        )));
+    }
+
+    let key = derive_archive_key(passphrase, &archive.header.kdf)?;
+    let plaintext = decrypt(
+        &key,
+        &header_aad(&archive.header)?,
+        &archive.header.nonce,
+        &archive.ciphertext,
+    )
+    .map_err(|_| RuntimeError::Crypto("backup passphrase is wrong or archive was modified".to_string()))?;
+    let payload: serde_json::Value =
+        serde_json::from_slice(&plaintext).map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+    let payload = migrate_payload(payload, archive.header.schema_version)?;
+    let payload: BackupPayload =
+        serde_json::from_value(payload).map_err(|err| RuntimeError::SchemaValidation(err.to_string()))?;
+    Ok((archive.header, payload))
+}
+
+/// Validates and restores an archive into the local database.
+///
+/// Every recipe is checked like an install on `device` against `registry`;
+/// rejected recipes are listed in `ImportReport::recipes_rejected` and their
+/// config, state and grants are not restored.
+///
+/// Grants for sensitive permissions are never restored; they are returned in
+/// `ImportReport::pending_consent` so the host can ask the user again. A grant
+/// the user revoked locally stays revoked whatever the archive says.
+pub fn import_backup(
+    conn: &Connection,
+    kek: &KeyEncryptionKey,
+    archive: &[u8],
+    passphrase: &str,
+    mode: ImportMode,
+    device: &DeviceMeta,
+    registry: &ConnectorRegistry,
+) -> RuntimeResult<ImportReport> {
+    let (_, payload) = read_backup(archive, passphrase)?;
+    let mut report = ImportReport::default();
+
+    let context = InstallContext::for_device(device);
+    let mut rejected = HashSet::new();
+    for recipe in &payload.recipes {
+        if let Err(err) = validate_restored_recipe(recipe, &context, registry) {
+            rejected.insert(recipe.id.as_str());
+            report.recipes_rejected.push(RejectedRecipe {
+                id: recipe.id.clone(),
+                reason: err.to_string(),
+            });
+        }
+    }
+
+    let tx = conn.unchecked_transaction().map_err(storage_err)?;
+    if mode == ImportMode::Replace {
+        for table in [
+            "recipes",
+            "recipe_config",
+            "state_kv",
+            "permissions_grants",
+            "execution_logs",
+            "captured_media",
+        ] {
+            tx.execute(&format!("DELETE FROM {}", table), []).map_err(storage_err)?;
+        }
+    }
+
+    for recipe in payload.recipes.iter().filter(|recipe| !rejected.contains(recipe.id.as_str())) {
+        tx.execute(
+            "INSERT INTO recipes (id, manifest, flow, enabled, scope) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(id) DO UPDATE SET manifest = excluded.manifest, flow = excluded.flow,
+               enabled = excluded.enabled, scope = excluded.scope",
+            params![recipe.id, recipe.manifest, recipe.flow, recipe.enabled, recipe.scope],
+        )
+        .map_err(storage_err)?;
+        report.recipes_restored += 1;
+    }
+
+    for entry in payload.config.iter().filter(|entry| !rejected.contains(entry.recipe_id.as_str())) {
+        put_stored_config(&tx, &entry.recipe_id, &entry.values)?;
+        report.config_restored += 1;
+    }
+
+    for entry in payload.state.iter().filter(|entry| !rejected.contains(entry.recipe_id.as_str())) {
+        put_state(&tx, kek, &entry.recipe_id, &entry.key, &entry.value)?;
+        report.state_restored += 1;
+    }
+
+    for grant in payload.grants {
+        if rejected.contains(grant.recipe_id.as_str()) {
+            continue;
+        }
+        if is_sensitive_permission(&grant.permission) {
+            report.pending_consent.push(grant);
+            continue;
+        }
+        tx.execute(
+            "INSERT INTO permissions_grants (recipe_id, permission, manifest_version, granted_at, expires_at, revoked_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(recipe_id, permission) DO UPDATE SET manifest_version = excluded.manifest_version,
+               granted_at = excluded.granted_at, expires_at = excluded.expires_at,
+               revoked_at = COALESCE(permissions_grants.revoked_at, excluded.revoked_at)",
+            params![
+                grant.recipe_id,
+                grant.permission,
+                grant.manifest_version,
+                grant.granted_at,
+                grant.expires_at,
+                grant.revoked_at
+            ],
+        )
+        .map_err(storage_err)?;
+        report.grants_restored += 1;
+    }
+
+    for log in payload.logs.iter().flatten() {
+        if run_recorded(&tx, "execution_logs", &log.recipe_id, &log.run_id)? {
+            continue;
+        }
+        append_execution_log(&tx, kek, log)?;
+        report.logs_restored += 1;
+    }
+
+    let media = payload.media.unwrap_or_default();
+    let mut recorded_runs = HashSet::new();
+    for entry in &media {
+        if run_recorded(&tx, "captured_media", &entry.recipe_id, &entry.run_id)? {
+            recorded_runs.insert((entry.recipe_id.as_str(), entry.run_id.as_str()));
+        }
+    }
+    for entry in &media {
+        if recorded_runs.contains(&(entry.recipe_id.as_str(), entry.run_id.as_str())) {
+            continue;
+        }
+        put_media_metadata(&tx, kek, &entry.recipe_id, &entry.run_id, &entry.media)?;
+        report.media_restored += 1;
+    }
+
+    let local_policy = load_policy_settings(&tx)?;
+    if mode == ImportMode::Replace && local_policy != payload.policy {
+        if payload.policy.is_at_least_as_strict_as(&local_policy) {
+            write_policy_settings(&tx, &payload.policy, BACKUP_IMPORT_ACTOR)?;
+            report.policy_restored = true;
+        } else {
+            report.policy_skipped = true;
+        }
+    }
+
+    tx.commit().map_err(storage_err)?;
+    Ok(report)
+}
@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::recipe::config::resolve_config;
+use crate::recipe::manifest::Manifest;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+fn storage_err(err: rusqlite::Error) -> RuntimeError {
+    RuntimeError::Storage(err.to_string())
+}
+
+fn serialization_err(err: serde_json::Error) -> RuntimeError {
+    RuntimeError::Serialization(err.to_string())
+}
+
+fn stored_values(conn: &Connection, recipe_id: &str) -> RuntimeResult<BTreeMap<String, serde_json::Value>> {
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT values_json FROM recipe_config WHERE recipe_id = ?1",
+            params![recipe_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(storage_err)?;
+    match stored {
+        Some(json) => serde_json::from_str(&json).map_err(serialization_err),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+/// Validates and stores the user's config values for an installed recipe.
+///
+/// Only the values the user set are stored, so defaults follow recipe updates.
+/// Returns the resolved config including defaults.
+pub fn save_recipe_config(
+    conn: &Connection,
+    manifest: &Manifest,
+    values: &BTreeMap<String, serde_json::Value>,
+) -> RuntimeResult<BTreeMap<String, serde_json::Value>> {
+    let resolved = resolve_config(manifest, values)?;
+    put_stored_config(conn, &manifest.id, values)?;
+    Ok(resolved)
+}
+
+/// Stores user config values as-is, e.g. when restoring a backup.
+///
+/// `load_recipe_config` still validates them against the manifest on use.
+pub fn put_stored_config(
+    conn: &Connection,
+    recipe_id: &str,
+    values: &BTreeMap<String, serde_json::Value>,
+) -> RuntimeResult<()> {
+    let json = serde_json::to_string(values).map_err(serialization_err)?;
+    conn.execute(
+        "INSERT INTO recipe_config (recipe_id, values_json, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(recipe_id) DO UPDATE SET values_json = excluded.values_json, updated_at = excluded.updated_at",
+        params![recipe_id, json, Utc::now().to_rfc3339()],
+    )
+    .map_err(storage_err)?;
+    Ok(())
+}
+
+/// Lists the stored (unresolved) config values of every recipe, by recipe id.
+pub fn list_stored_config(conn: &Connection) -> RuntimeResult<Vec<(String, BTreeMap<String, serde_json::Value>)>> {
+    let rows: Vec<(String, String)> = {
+        let mut stmt = conn
+            .prepare("SELECT recipe_id, values_json FROM recipe_config ORDER BY recipe_id")
+            .map_err(storage_err)?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(storage_err)?;
+        rows.collect::<Result<_, _>>().map_err(storage_err)?
+    };
+    rows.into_iter()
+        .map(|(recipe_id, json)| Ok((recipe_id, serde_json::from_str(&json).map_err(serialization_err)?)))
+        .collect()
+}
+
+/// Loads the resolved config of a recipe against its current manifest.
+///
+/// Stored keys the manifest no longer declares are ignored; stored values that
+/// no longer validate fail, so the user is asked to reconfigure.
+pub fn load_recipe_config(conn: &Connection, manifest: &Manifest) -> RuntimeResult<BTreeMap<String, serde_json::Value>> {
+    let mut values = stored_values(conn, &manifest.id)?;
+    values.retain(|key, _| manifest.config.iter().any(|param| &param.key == key));
+    resolve_config(manifest, &values)
+}
@@ -1,8 +1,168 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
-/// Hashes encrypted payload envelope input.
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Envelope format marker written into every sealed payload.
+pub const ENVELOPE_VERSION: &str = "xchacha20poly1305.v1";
+
+const KEY_WRAP_AAD_PREFIX: &[u8] = b"arquent.dek.wrap\0";
+
+/// Hashes a blob with SHA-256 for content addressing.
 pub fn hash_blob(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
     hasher.update(data);
     format!("{:x}", hasher.finalize())
 }
+
+/// Host-provided key-encryption key (KEK), e.g. sourced from the OS keychain.
+///
+/// The KEK never encrypts row data directly; it only wraps data-encryption keys.
+pub struct KeyEncryptionKey {
+    id: String,
+    key: [u8; 32],
+}
+
+impl KeyEncryptionKey {
+    pub fn new(id: impl Into<String>, key: [u8; 32]) -> Self {
+        Self { id: id.into(), key }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// Data-encryption key (DEK) used to seal row payloads.
+pub struct DataKey {
+    id: String,
+    key: [u8; 32],
+}
+
+impl DataKey {
+    /// Generates a fresh random DEK with a random key id.
+    pub fn generate() -> Self {
+        let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+        let id_seed = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        Self {
+            id: format!("dek_{}", &hash_blob(id_seed.as_slice())[..16]),
+            key: key.into(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// Sealed payload stored in place of plaintext JSON columns.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Envelope {
+    pub v: String,
+    pub kid: String,
+    pub nonce: String,
+    pub ct: String,
+}
+
+impl Envelope {
+    /// Parses a stored column value, rejecting anything that is not a sealed envelope.
+    pub fn parse(text: &str) -> RuntimeResult<Self> {
+        let envelope: Envelope = serde_json::from_str(text)
+            .map_err(|_| RuntimeError::Crypto("stored value is not an encrypted envelope".to_string()))?;
+        if envelope.v != ENVELOPE_VERSION {
+            return Err(RuntimeError::Crypto(format!(
+                "unsupported envelope version: {}",
+                envelope.v
+            )));
+        }
+        Ok(envelope)
+    }
+
+    pub fn to_json(&self) -> RuntimeResult<String> {
+        serde_json::to_string(self).map_err(|err| RuntimeError::Serialization(err.to_string()))
+    }
+}
+
+pub(crate) fn encrypt(key: &[u8; 32], aad: &[u8], plaintext: &[u8]) -> RuntimeResult<(String, String)> {
+    let cipher = XChaCha20Poly1305::new(key.into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plaintext, aad })
+        .map_err(|_| RuntimeError::Crypto("encryption failed".to_string()))?;
+    Ok((STANDARD.encode(nonce), STANDARD.encode(ciphertext)))
+}
+
+pub(crate) fn decrypt(key: &[u8; 32], aad: &[u8], nonce_b64: &str, ciphertext_b64: &str) -> RuntimeResult<Vec<u8>> {
+    let nonce_bytes = STANDARD
+        .decode(nonce_b64)
+        .map_err(|_| RuntimeError::Crypto("envelope nonce is not base64".to_string()))?;
+    if nonce_bytes.len() != 24 {
+        return Err(RuntimeError::Crypto("envelope nonce has invalid length".to_string()));
+    }
+    let ciphertext = STANDARD
+        .decode(ciphertext_b64)
+        .map_err(|_| RuntimeError::Crypto("envelope ciphertext is not base64".to_string()))?;
+    let cipher = XChaCha20Poly1305::new(key.into());
+    cipher
+        .decrypt(
+            XNonce::from_slice(&nonce_bytes),
+            Payload {
+                msg: ciphertext.as_slice(),
+                aad,
+            },
+        )
+        .map_err(|_| RuntimeError::Crypto("envelope authentication failed".to_string()))
+}
+
+/// Encrypts a payload with the DEK, binding it to `aad` (table and row identity).
+pub fn seal(key: &DataKey, aad: &[u8], plaintext: &[u8]) -> RuntimeResult<Envelope> {
+    let (nonce, ct) = encrypt(&key.key, aad, plaintext)?;
+    Ok(Envelope {
+        v: ENVELOPE_VERSION.to_string(),
+        kid: key.id.clone(),
+        nonce,
+        ct,
+    })
+}
+
+/// Decrypts and authenticates an envelope sealed with `key` under the same `aad`.
+pub fn open(key: &DataKey, aad: &[u8], envelope: &Envelope) -> RuntimeResult<Vec<u8>> {
+    if envelope.kid != key.id {
+        return Err(RuntimeError::Crypto(format!(
+            "envelope key id {} does not match data key {}",
+            envelope.kid, key.id
+        )));
+    }
+    decrypt(&key.key, aad, &envelope.nonce, &envelope.ct)
+}
+
+fn key_wrap_aad(dek_id: &str) -> Vec<u8> {
+    let mut aad = KEY_WRAP_AAD_PREFIX.to_vec();
+    aad.extend_from_slice(dek_id.as_bytes());
+    aad
+}
+
+/// Wraps a DEK under the KEK as `<nonce_b64>.<ciphertext_b64>`.
+pub fn wrap_data_key(kek: &KeyEncryptionKey, dek: &DataKey) -> RuntimeResult<String> {
+    let (nonce, ct) = encrypt(&kek.key, &key_wrap_aad(&dek.id), &dek.key)?;
+    Ok(format!("{}.{}", nonce, ct))
+}
+
+/// Unwraps a DEK previously produced by [`wrap_data_key`].
+pub fn unwrap_data_key(kek: &KeyEncryptionKey, dek_id: &str, wrapped: &str) -> RuntimeResult<DataKey> {
+    let (nonce, ct) = wrapped
+        .split_once('.')
+        .ok_or_else(|| RuntimeError::Crypto("wrapped data key is malformed".to_string()))?;
+    let key_bytes = decrypt(&kek.key, &key_wrap_aad(dek_id), nonce, ct)?;
+    let key: [u8; 32] = key_bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| RuntimeError::Crypto("wrapped data key has invalid length".to_string()))?;
+    Ok(DataKey {
+        id: dek_id.to_string(),
+        key,
+    })
+}
@@ -1,14 +1,11 @@
 use rusqlite::Connection;
 
-use crate::storage::migrations::MIGRATIONS;
+use crate::storage::migrations::migrate;
 use crate::types::errors::{RuntimeError, RuntimeResult};
 
-/// Opens SQLite and ensures required tables exist.
+/// Opens SQLite and migrates it to the current schema version.
 pub fn initialize_database(path: &str) -> RuntimeResult<Connection> {
     let conn = Connection::open(path).map_err(|err| RuntimeError::Storage(err.to_string()))?;
-    for statement in MIGRATIONS {
-        conn.execute(statement, [])
-            .map_err(|err| RuntimeError::Storage(err.to_string()))?;
-    }
+    migrate(&conn)?;
     Ok(conn)
 }
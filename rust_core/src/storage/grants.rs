@@ -0,0 +1,118 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, Row};
+
+use crate::engine::grants::{declared_permission_keys, PermissionGrant};
+use crate::recipe::manifest::Manifest;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+fn storage_err(err: rusqlite::Error) -> RuntimeError {
+    RuntimeError::Storage(err.to_string())
+}
+
+fn grant_from_row(row: &Row<'_>) -> rusqlite::Result<PermissionGrant> {
+    Ok(PermissionGrant {
+        recipe_id: row.get(0)?,
+        permission: row.get(1)?,
+        manifest_version: row.get(2)?,
+        granted_at: row.get(3)?,
+        expires_at: row.get(4)?,
+        revoked_at: row.get(5)?,
+    })
+}
+
+/// Records (or renews) a user grant for one permission of a recipe.
+pub fn grant_permission(
+    conn: &Connection,
+    recipe_id: &str,
+    permission: &str,
+    manifest_version: &str,
+    expires_at: Option<&str>,
+) -> RuntimeResult<PermissionGrant> {
+    let grant = PermissionGrant {
+        recipe_id: recipe_id.to_string(),
+        permission: permission.to_string(),
+        manifest_version: manifest_version.to_string(),
+        granted_at: Utc::now().to_rfc3339(),
+        expires_at: expires_at.map(str::to_string),
+        revoked_at: None,
+    };
+    conn.execute(
+        "INSERT INTO permissions_grants (recipe_id, permission, manifest_version, granted_at, expires_at, revoked_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, NULL)
+         ON CONFLICT(recipe_id, permission) DO UPDATE SET
+           manifest_version = excluded.manifest_version,
+           granted_at = excluded.granted_at,
+           expires_at = excluded.expires_at,
+           revoked_at = NULL",
+        params![
+            grant.recipe_id,
+            grant.permission,
+            grant.manifest_version,
+            grant.granted_at,
+            grant.expires_at
+        ],
+    )
+    .map_err(storage_err)?;
+    Ok(grant)
+}
+
+/// Grants every permission the manifest declares, e.g. after the install consent screen.
+pub fn grant_manifest_permissions(
+    conn: &Connection,
+    manifest: &Manifest,
+    expires_at: Option<&str>,
+) -> RuntimeResult<Vec<PermissionGrant>> {
+    declared_permission_keys(&manifest.permissions)
+        .into_iter()
+        .map(|permission| grant_permission(conn, &manifest.id, permission, &manifest.version, expires_at))
+        .collect()
+}
+
+/// Revokes a single permission grant. Returns false when no active grant existed.
+pub fn revoke_permission(conn: &Connection, recipe_id: &str, permission: &str) -> RuntimeResult<bool> {
+    let updated = conn
+        .execute(
+            "UPDATE permissions_grants SET revoked_at = ?1
+             WHERE recipe_id = ?2 AND permission = ?3 AND revoked_at IS NULL",
+            params![Utc::now().to_rfc3339(), recipe_id, permission],
+        )
+        .map_err(storage_err)?;
+    Ok(updated > 0)
+}
+
+/// Moves a recipe's unrevoked grants to `manifest_version`, e.g. after an
+/// update that did not escalate them. Returns the number of grants moved.
+pub fn repin_grants(conn: &Connection, recipe_id: &str, manifest_version: &str) -> RuntimeResult<usize> {
+    conn.execute(
+        "UPDATE permissions_grants SET manifest_version = ?1
+         WHERE recipe_id = ?2 AND revoked_at IS NULL",
+        params![manifest_version, recipe_id],
+    )
+    .map_err(storage_err)
+}
+
+/// Lists all grants (active, expired and revoked) recorded for a recipe.
+pub fn list_grants(conn: &Connection, recipe_id: &str) -> RuntimeResult<Vec<PermissionGrant>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT recipe_id, permission, manifest_version, granted_at, expires_at, revoked_at
+             FROM permissions_grants WHERE recipe_id = ?1 ORDER BY permission",
+        )
+        .map_err(storage_err)?;
+    let rows = stmt
+        .query_map(params![recipe_id], grant_from_row)
+        .map_err(storage_err)?;
+    rows.collect::<Result<_, _>>().map_err(storage_err)
+}
+
+/// Lists every recorded grant across all recipes.
+pub fn list_all_grants(conn: &Connection) -> RuntimeResult<Vec<PermissionGrant>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT recipe_id, permission, manifest_version, granted_at, expires_at, revoked_at
+             FROM permissions_grants ORDER BY recipe_id, permission",
+        )
+        .map_err(storage_err)?;
+    let rows = stmt.query_map([], grant_from_row).map_err(storage_err)?;
+    rows.collect::<Result<_, _>>().map_err(storage_err)
+}
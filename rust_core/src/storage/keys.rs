@@ -0,0 +1,119 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::storage::crypto::{unwrap_data_key, wrap_data_key, DataKey, KeyEncryptionKey};
+use crate::storage::records::referenced_data_key_ids;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+fn storage_err(err: rusqlite::Error) -> RuntimeError {
+    RuntimeError::Storage(err.to_string())
+}
+
+fn insert_data_key(conn: &Connection, kek: &KeyEncryptionKey, dek: &DataKey) -> RuntimeResult<()> {
+    conn.execute(
+        "INSERT INTO data_keys (id, kek_id, wrapped_key, active, created_at) VALUES (?1, ?2, ?3, 1, ?4)",
+        params![dek.id(), kek.id(), wrap_data_key(kek, dek)?, Utc::now().to_rfc3339()],
+    )
+    .map_err(storage_err)?;
+    Ok(())
+}
+
+/// Loads a DEK by id, unwrapping it with the host KEK.
+pub fn data_key(conn: &Connection, kek: &KeyEncryptionKey, dek_id: &str) -> RuntimeResult<DataKey> {
+    let row: Option<(String, String)> = conn
+        .query_row(
+            "SELECT kek_id, wrapped_key FROM data_keys WHERE id = ?1",
+            params![dek_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(storage_err)?;
+    let (kek_id, wrapped) =
+        row.ok_or_else(|| RuntimeError::Crypto(format!("unknown data key: {}", dek_id)))?;
+    if kek_id != kek.id() {
+        return Err(RuntimeError::Crypto(format!(
+            "data key {} is wrapped by key-encryption key {}",
+            dek_id, kek_id
+        )));
+    }
+    unwrap_data_key(kek, dek_id, &wrapped)
+}
+
+/// Returns the active DEK, creating and wrapping one on first use.
+pub fn active_data_key(conn: &Connection, kek: &KeyEncryptionKey) -> RuntimeResult<DataKey> {
+    let active_id: Option<String> = conn
+        .query_row(
+            "SELECT id FROM data_keys WHERE active = 1 ORDER BY created_at DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(storage_err)?;
+    match active_id {
+        Some(id) => data_key(conn, kek, &id),
+        None => {
+            let dek = DataKey::generate();
+            insert_data_key(conn, kek, &dek)?;
+            Ok(dek)
+        }
+    }
+}
+
+/// Creates a new active DEK and retires the previous one.
+///
+/// Existing rows stay readable with the retired key until
+/// `records::reencrypt_stale_rows` has moved them to the new key.
+pub fn rotate_data_key(conn: &Connection, kek: &KeyEncryptionKey) -> RuntimeResult<DataKey> {
+    let dek = DataKey::generate();
+    conn.execute("UPDATE data_keys SET active = 0 WHERE active = 1", [])
+        .map_err(storage_err)?;
+    insert_data_key(conn, kek, &dek)?;
+    Ok(dek)
+}
+
+/// Re-wraps every DEK under a new KEK. Row payloads are untouched.
+pub fn rewrap_data_keys(
+    conn: &Connection,
+    old_kek: &KeyEncryptionKey,
+    new_kek: &KeyEncryptionKey,
+) -> RuntimeResult<usize> {
+    let ids: Vec<String> = {
+        let mut stmt = conn
+            .prepare("SELECT id FROM data_keys WHERE kek_id = ?1")
+            .map_err(storage_err)?;
+        let rows = stmt
+            .query_map(params![old_kek.id()], |row| row.get(0))
+            .map_err(storage_err)?;
+        rows.collect::<Result<_, _>>().map_err(storage_err)?
+    };
+
+    for id in &ids {
+        let dek = data_key(conn, old_kek, id)?;
+        conn.execute(
+            "UPDATE data_keys SET kek_id = ?1, wrapped_key = ?2 WHERE id = ?3",
+            params![new_kek.id(), wrap_data_key(new_kek, &dek)?, id],
+        )
+        .map_err(storage_err)?;
+    }
+    Ok(ids.len())
+}
+
+/// Deletes retired DEKs that no longer protect any row.
+pub fn purge_retired_data_keys(conn: &Connection) -> RuntimeResult<usize> {
+    let referenced_ids = referenced_data_key_ids(conn)?;
+    let retired: Vec<String> = {
+        let mut stmt = conn
+            .prepare("SELECT id FROM data_keys WHERE active = 0")
+            .map_err(storage_err)?;
+        let rows = stmt.query_map([], |row| row.get(0)).map_err(storage_err)?;
+        rows.collect::<Result<_, _>>().map_err(storage_err)?
+    };
+
+    let mut purged = 0;
+    for id in retired.iter().filter(|id| !referenced_ids.contains(id)) {
+        purged += conn
+            .execute("DELETE FROM data_keys WHERE id = ?1", params![id])
+            .map_err(storage_err)?;
+    }
+    Ok(purged)
+}
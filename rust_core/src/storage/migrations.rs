@@ -1,9 +1,137 @@
-/// SQLite schema migration statements.
+use rusqlite::Connection;
+
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Current schema version, stored in `PRAGMA user_version` and recorded in
+/// backups so imports can be migrated.
+///
+/// 1: initial layout, one `grants_json` row per recipe.
+/// 2: one `permissions_grants` row per recipe and permission; backups also
+///    carry recipe config and captured-media metadata.
+/// 3: `audit_log` records the signer of verified packages in its own columns.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// SQLite statements creating the current layout.
 pub const MIGRATIONS: &[&str] = &[
     "CREATE TABLE IF NOT EXISTS recipes (id TEXT PRIMARY KEY, manifest TEXT NOT NULL, flow TEXT NOT NULL, enabled INTEGER NOT NULL, scope TEXT NOT NULL)",
-    "CREATE TABLE IF NOT EXISTS permissions_grants (recipe_id TEXT PRIMARY KEY, grants_json TEXT NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS permissions_grants (recipe_id TEXT NOT NULL, permission TEXT NOT NULL, manifest_version TEXT NOT NULL, granted_at TEXT NOT NULL, expires_at TEXT, revoked_at TEXT, PRIMARY KEY(recipe_id, permission))",
     "CREATE TABLE IF NOT EXISTS execution_logs (id INTEGER PRIMARY KEY AUTOINCREMENT, recipe_id TEXT NOT NULL, run_id TEXT NOT NULL, log_json TEXT NOT NULL, created_at TEXT NOT NULL)",
     "CREATE TABLE IF NOT EXISTS state_kv (recipe_id TEXT NOT NULL, key TEXT NOT NULL, value_json TEXT NOT NULL, PRIMARY KEY(recipe_id, key))",
     "CREATE TABLE IF NOT EXISTS trigger_bindings (recipe_id TEXT NOT NULL, trigger_type TEXT NOT NULL, binding_json TEXT NOT NULL)",
     "CREATE TABLE IF NOT EXISTS policy_settings (id INTEGER PRIMARY KEY CHECK (id = 1), settings_json TEXT NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS policy_settings_history (id INTEGER PRIMARY KEY AUTOINCREMENT, changed_by TEXT NOT NULL, changed_at TEXT NOT NULL, old_settings_json TEXT NOT NULL, new_settings_json TEXT NOT NULL)",
+    "CREATE TRIGGER IF NOT EXISTS policy_settings_history_no_update BEFORE UPDATE ON policy_settings_history BEGIN SELECT RAISE(ABORT, 'policy_settings_history is append-only'); END",
+    "CREATE TRIGGER IF NOT EXISTS policy_settings_history_no_delete BEFORE DELETE ON policy_settings_history BEGIN SELECT RAISE(ABORT, 'policy_settings_history is append-only'); END",
+    "CREATE TABLE IF NOT EXISTS audit_log (seq INTEGER PRIMARY KEY, recipe_id TEXT NOT NULL, run_id TEXT NOT NULL, event TEXT NOT NULL, capability TEXT NOT NULL, recorded_at TEXT NOT NULL, prev_hash TEXT NOT NULL, entry_hash TEXT NOT NULL, signer_publisher_id TEXT, signer_key_id TEXT, signer_digest_version TEXT, signer_signed_at TEXT)",
+    "CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END",
+    "CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END",
+    "CREATE TABLE IF NOT EXISTS data_keys (id TEXT PRIMARY KEY, kek_id TEXT NOT NULL, wrapped_key TEXT NOT NULL, active INTEGER NOT NULL, created_at TEXT NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS captured_media (id INTEGER PRIMARY KEY AUTOINCREMENT, recipe_id TEXT NOT NULL, run_id TEXT NOT NULL, meta_json TEXT NOT NULL, created_at TEXT NOT NULL)",
+    "CREATE TABLE IF NOT EXISTS recipe_config (recipe_id TEXT PRIMARY KEY, values_json TEXT NOT NULL, updated_at TEXT NOT NULL)",
 ];
+
+/// Upgrade steps keyed by the version they produce. Databases created before
+/// `user_version` was recorded report 0 and are treated as version 1.
+///
+/// Steps run after `MIGRATIONS`, so tables a step touches may already have
+/// been created in the current layout and must be left alone.
+const UPGRADES: &[(u32, Upgrade)] = &[(2, split_grants_per_permission), (3, add_audit_signer_columns)];
+
+type Upgrade = fn(&Connection) -> RuntimeResult<()>;
+
+/// Splits version-1 `grants_json` rows into one row per permission.
+///
+/// The old column held a JSON array of permission keys, or an object mapping
+/// keys to booleans. Converted grants are pinned to the installed manifest
+/// version so they stay valid until the recipe is updated.
+const GRANTS_PER_PERMISSION: &str = "
+    ALTER TABLE permissions_grants RENAME TO permissions_grants_v1;
+    CREATE TABLE permissions_grants (recipe_id TEXT NOT NULL, permission TEXT NOT NULL, manifest_version TEXT NOT NULL, granted_at TEXT NOT NULL, expires_at TEXT, revoked_at TEXT, PRIMARY KEY(recipe_id, permission));
+    INSERT OR IGNORE INTO permissions_grants (recipe_id, permission, manifest_version, granted_at, expires_at, revoked_at)
+        SELECT old.recipe_id,
+               CASE json_type(old.grants_json) WHEN 'array' THEN entry.value ELSE entry.key END,
+               COALESCE(json_extract(recipes.manifest, '$.version'), '0.0.0'),
+               strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now'),
+               NULL,
+               NULL
+        FROM permissions_grants_v1 AS old
+        JOIN json_each(old.grants_json) AS entry
+        LEFT JOIN recipes ON recipes.id = old.recipe_id
+        WHERE json_valid(old.grants_json)
+          AND CASE json_type(old.grants_json)
+                WHEN 'array' THEN entry.type = 'text'
+                ELSE entry.type = 'true'
+              END;
+    DROP TABLE permissions_grants_v1;
+";
+
+const AUDIT_SIGNER_COLUMNS: [&str; 4] = [
+    "signer_publisher_id",
+    "signer_key_id",
+    "signer_digest_version",
+    "signer_signed_at",
+];
+
+fn storage_err(err: rusqlite::Error) -> RuntimeError {
+    RuntimeError::Storage(err.to_string())
+}
+
+fn split_grants_per_permission(conn: &Connection) -> RuntimeResult<()> {
+    if !has_column(conn, "permissions_grants", "grants_json")? {
+        return Ok(());
+    }
+    conn.execute_batch(GRANTS_PER_PERMISSION).map_err(storage_err)
+}
+
+fn add_audit_signer_columns(conn: &Connection) -> RuntimeResult<()> {
+    for column in AUDIT_SIGNER_COLUMNS {
+        if !has_column(conn, "audit_log", column)? {
+            conn.execute(&format!("ALTER TABLE audit_log ADD COLUMN {} TEXT", column), [])
+                .map_err(storage_err)?;
+        }
+    }
+    Ok(())
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> RuntimeResult<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+    .map_err(storage_err)
+}
+
+/// Brings the database to `SCHEMA_VERSION`, upgrading older layouts in place.
+pub fn migrate(conn: &Connection) -> RuntimeResult<()> {
+    let stored: u32 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(storage_err)?;
+    if stored > SCHEMA_VERSION {
+        return Err(RuntimeError::Storage(format!(
+            "database schema version {} is newer than supported version {}",
+            stored, SCHEMA_VERSION
+        )));
+    }
+    // Unversioned databases from before the per-permission grants layout still
+    // carry the `grants_json` column; fresh ones get the current layout below.
+    let from_version = match stored {
+        0 if has_column(conn, "permissions_grants", "grants_json")? => 1,
+        0 => SCHEMA_VERSION,
+        version => version,
+    };
+
+    let tx = conn.unchecked_transaction().map_err(storage_err)?;
+    for statement in MIGRATIONS {
+        tx.execute(statement, []).map_err(storage_err)?;
+    }
+    for (version, upgrade) in UPGRADES {
+        if from_version < *version {
+            upgrade(&tx)?;
+        }
+    }
+    tx.pragma_update(None, "user_version", SCHEMA_VERSION)
+        .map_err(storage_err)?;
+    tx.commit().map_err(storage_err)
+}
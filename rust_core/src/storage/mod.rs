@@ -1,3 +1,11 @@
+pub mod audit;
+pub mod backup;
+pub mod config;
 pub mod crypto;
 pub mod db;
+pub mod grants;
+pub mod keys;
 pub mod migrations;
+pub mod policy;
+pub mod recipes;
+pub mod records;
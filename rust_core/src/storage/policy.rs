@@ -0,0 +1,103 @@
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::policy::PolicySettings;
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// One append-only entry in the policy change history.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PolicyChange {
+    pub changed_by: String,
+    pub changed_at: String,
+    pub old_settings: PolicySettings,
+    pub new_settings: PolicySettings,
+}
+
+fn storage_err(err: rusqlite::Error) -> RuntimeError {
+    RuntimeError::Storage(err.to_string())
+}
+
+fn parse_settings(json: &str) -> RuntimeResult<PolicySettings> {
+    serde_json::from_str(json).map_err(|err| RuntimeError::Serialization(err.to_string()))
+}
+
+fn settings_json(settings: &PolicySettings) -> RuntimeResult<String> {
+    serde_json::to_string(settings).map_err(|err| RuntimeError::Serialization(err.to_string()))
+}
+
+/// Loads the active policy settings, falling back to defaults when none are stored.
+pub fn load_policy_settings(conn: &Connection) -> RuntimeResult<PolicySettings> {
+    let stored: Option<String> = conn
+        .query_row("SELECT settings_json FROM policy_settings WHERE id = 1", [], |row| row.get(0))
+        .optional()
+        .map_err(storage_err)?;
+    let settings = match stored {
+        Some(json) => parse_settings(&json)?,
+        None => PolicySettings::default(),
+    };
+    settings.validate()?;
+    Ok(settings)
+}
+
+/// Validates and stores new policy settings, appending the change to the history.
+pub fn save_policy_settings(conn: &Connection, settings: &PolicySettings, changed_by: &str) -> RuntimeResult<()> {
+    let tx = conn.unchecked_transaction().map_err(storage_err)?;
+    write_policy_settings(&tx, settings, changed_by)?;
+    tx.commit().map_err(storage_err)
+}
+
+/// Same as [`save_policy_settings`] for callers that already hold a transaction.
+pub(crate) fn write_policy_settings(
+    conn: &Connection,
+    settings: &PolicySettings,
+    changed_by: &str,
+) -> RuntimeResult<()> {
+    if changed_by.trim().is_empty() {
+        return Err(RuntimeError::SchemaValidation("changed_by is required".to_string()));
+    }
+    settings.validate()?;
+
+    let old_settings = load_policy_settings(conn)?;
+    let new_json = settings_json(settings)?;
+    conn.execute(
+        "INSERT INTO policy_settings (id, settings_json) VALUES (1, ?1)
+         ON CONFLICT(id) DO UPDATE SET settings_json = excluded.settings_json",
+        params![new_json],
+    )
+    .map_err(storage_err)?;
+    conn.execute(
+        "INSERT INTO policy_settings_history (changed_by, changed_at, old_settings_json, new_settings_json)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![changed_by, Utc::now().to_rfc3339(), settings_json(&old_settings)?, new_json],
+    )
+    .map_err(storage_err)?;
+    Ok(())
+}
+
+/// Lists policy changes, oldest first.
+pub fn list_policy_history(conn: &Connection) -> RuntimeResult<Vec<PolicyChange>> {
+    let rows: Vec<(String, String, String, String)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT changed_by, changed_at, old_settings_json, new_settings_json
+                 FROM policy_settings_history ORDER BY id",
+            )
+            .map_err(storage_err)?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .map_err(storage_err)?;
+        rows.collect::<Result<_, _>>().map_err(storage_err)?
+    };
+
+    rows.into_iter()
+        .map(|(changed_by, changed_at, old_json, new_json)| {
+            Ok(PolicyChange {
+                changed_by,
+                changed_at,
+                old_settings: parse_settings(&old_json)?,
+                new_settings: parse_settings(&new_json)?,
+            })
+        })
+        .collect()
+}
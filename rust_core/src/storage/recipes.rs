@@ -0,0 +1,114 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::connectors::registry::ConnectorRegistry;
+use crate::recipe::flow::RecipeFlow;
+use crate::recipe::install::{validate_install, InstallContext};
+use crate::engine::grants::declared_permission_keys;
+use crate::recipe::manifest::{Manifest, PermissionChangeKind, PermissionDiff, PermissionSet};
+use crate::recipe::model::RecipeModel;
+use crate::storage::audit::append_signature_verification;
+use crate::storage::grants::{repin_grants, revoke_permission};
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+fn storage_err(err: rusqlite::Error) -> RuntimeError {
+    RuntimeError::Storage(err.to_string())
+}
+
+fn serialization_err(err: serde_json::Error) -> RuntimeError {
+    RuntimeError::Serialization(err.to_string())
+}
+
+/// What an install changed relative to the previously installed version.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecipeUpdate {
+    /// Version replaced by this install; `None` for a first install.
+    pub previous_version: Option<String>,
+    pub diff: PermissionDiff,
+    pub risk_escalated: bool,
+    /// Permissions whose grants were revoked and need fresh consent before the next run.
+    pub reconsent_required: Vec<String>,
+}
+
+/// Validates and stores a recipe, replacing any installed version of the same id.
+///
+/// A keyring-verified publisher in `context` is recorded in the audit chain
+/// in the same transaction.
+///
+/// On update, grants for escalated permissions (every declared one when the
+/// risk level rises) are revoked so the host must ask again; grants for
+/// removed permissions are revoked too. Narrowing keeps existing grants and
+/// re-pins them to the new manifest version.
+pub fn install_recipe(
+    conn: &Connection,
+    model: &RecipeModel,
+    context: &InstallContext,
+    registry: &ConnectorRegistry,
+    scope: &str,
+) -> RuntimeResult<RecipeUpdate> {
+    validate_install(model, context, registry)?;
+    let previous = load_recipe(conn, &model.manifest.id)?;
+    let manifest = serde_json::to_string(&model.manifest).map_err(serialization_err)?;
+    let flow = serde_json::to_string(&model.flow).map_err(serialization_err)?;
+
+    let tx = conn.unchecked_transaction().map_err(storage_err)?;
+    tx.execute(
+        "INSERT INTO recipes (id, manifest, flow, enabled, scope) VALUES (?1, ?2, ?3, 1, ?4)
+         ON CONFLICT(id) DO UPDATE SET manifest = excluded.manifest, flow = excluded.flow, scope = excluded.scope",
+        params![model.manifest.id, manifest, flow, scope],
+    )
+    .map_err(storage_err)?;
+    if let Some(publisher) = &context.verified_publisher {
+        append_signature_verification(&tx, &model.manifest.id, publisher)?;
+    }
+
+    let Some(previous) = previous else {
+        tx.commit().map_err(storage_err)?;
+        return Ok(RecipeUpdate::default());
+    };
+    let diff = PermissionSet::diff(&previous.manifest.permissions, &model.manifest.permissions);
+    let risk_escalated = model.manifest.risk_level > previous.manifest.risk_level;
+    let reconsent_required: Vec<String> = if risk_escalated {
+        declared_permission_keys(&model.manifest.permissions)
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    } else {
+        diff.escalated_permissions().into_iter().map(str::to_string).collect()
+    };
+    let removed = diff
+        .changes
+        .iter()
+        .filter(|change| change.kind == PermissionChangeKind::Removed)
+        .map(|change| change.permission.as_str());
+    for permission in reconsent_required.iter().map(String::as_str).chain(removed) {
+        revoke_permission(&tx, &model.manifest.id, permission)?;
+    }
+    repin_grants(&tx, &model.manifest.id, &model.manifest.version)?;
+    tx.commit().map_err(storage_err)?;
+
+    Ok(RecipeUpdate {
+        previous_version: Some(previous.manifest.version),
+        diff,
+        risk_escalated,
+        reconsent_required,
+    })
+}
+
+/// Loads an installed recipe by id.
+pub fn load_recipe(conn: &Connection, recipe_id: &str) -> RuntimeResult<Option<RecipeModel>> {
+    let row: Option<(String, String)> = conn
+        .query_row(
+            "SELECT manifest, flow FROM recipes WHERE id = ?1",
+            params![recipe_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(storage_err)?;
+    row.map(|(manifest, flow)| {
+        let manifest: Manifest = serde_json::from_str(&manifest).map_err(serialization_err)?;
+        let flow: RecipeFlow = serde_json::from_str(&flow).map_err(serialization_err)?;
+        Ok(RecipeModel { manifest, flow })
+    })
+    .transpose()
+}
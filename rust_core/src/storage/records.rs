@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::engine::logging::ExecutionLog;
+use crate::storage::crypto::{open, seal, DataKey, Envelope, KeyEncryptionKey};
+use crate::storage::keys::{active_data_key, data_key};
+use crate::types::datavalue::{DataValue, MediaRef};
+use crate::types::errors::{RuntimeError, RuntimeResult};
+
+/// Encrypted payload columns and the row columns bound into their AAD.
+const ENCRYPTED_COLUMNS: &[(&str, &str, [&str; 2])] = &[
+    ("state_kv", "value_json", ["recipe_id", "key"]),
+    ("execution_logs", "log_json", ["recipe_id", "run_id"]),
+    ("captured_media", "meta_json", ["recipe_id", "run_id"]),
+];
+
+fn storage_err(err: rusqlite::Error) -> RuntimeError {
+    RuntimeError::Storage(err.to_string())
+}
+
+fn row_aad(table: &str, first: &str, second: &str) -> Vec<u8> {
+    format!("{}\0{}\0{}", table, first, second).into_bytes()
+}
+
+fn seal_json<T: Serialize>(dek: &DataKey, aad: &[u8], value: &T) -> RuntimeResult<String> {
+    let plaintext = serde_json::to_vec(value).map_err(|err| RuntimeError::Serialization(err.to_string()))?;
+    seal(dek, aad, &plaintext)?.to_json()
+}
+
+/// Caches unwrapped DEKs while reading many rows.
+struct KeyCache<'a> {
+    conn: &'a Connection,
+    kek: &'a KeyEncryptionKey,
+    keys: HashMap<String, DataKey>,
+}
+
+impl<'a> KeyCache<'a> {
+    fn new(conn: &'a Connection, kek: &'a KeyEncryptionKey) -> Self {
+        Self {
+            conn,
+            kek,
+            keys: HashMap::new(),
+        }
+    }
+
+    fn open_text(&mut self, aad: &[u8], stored: &str) -> RuntimeResult<Vec<u8>> {
+        let envelope = Envelope::parse(stored)?;
+        if !self.keys.contains_key(&envelope.kid) {
+            let dek = data_key(self.conn, self.kek, &envelope.kid)?;
+            self.keys.insert(envelope.kid.clone(), dek);
+        }
+        let dek = &self.keys[&envelope.kid];
+        open(dek, aad, &envelope)
+    }
+
+    fn open_json<T: DeserializeOwned>(&mut self, aad: &[u8], stored: &str) -> RuntimeResult<T> {
+        let plaintext = self.open_text(aad, stored)?;
+        serde_json::from_slice(&plaintext).map_err(|err| RuntimeError::Serialization(err.to_string()))
+    }
+}
+
+/// Writes an encrypted state value for a recipe.
+pub fn put_state(
+    conn: &Connection,
+    kek: &KeyEncryptionKey,
+    recipe_id: &str,
+    key: &str,
+    value: &DataValue,
+) -> RuntimeResult<()> {
+    let dek = active_data_key(conn, kek)?;
+    let sealed = seal_json(&dek, &row_aad("state_kv", recipe_id, key), value)?;
+    conn.execute(
+        "INSERT INTO state_kv (recipe_id, key, value_json) VALUES (?1, ?2, ?3)
+         ON CONFLICT(recipe_id, key) DO UPDATE SET value_json = excluded.value_json",
+        params![recipe_id, key, sealed],
+    )
+    .map_err(storage_err)?;
+    Ok(())
+}
+
+/// Reads and decrypts a state value. Plaintext rows are rejected.
+pub fn get_state(
+    conn: &Connection,
+    kek: &KeyEncryptionKey,
+    recipe_id: &str,
+    key: &str,
+) -> RuntimeResult<Option<DataValue>> {
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT value_json FROM state_kv WHERE recipe_id = ?1 AND key = ?2",
+            params![recipe_id, key],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(storage_err)?;
+    stored
+        .map(|text| KeyCache::new(conn, kek).open_json(&row_aad("state_kv", recipe_id, key), &text))
+        .transpose()
+}
+
+/// Lists every decrypted state value as `(recipe_id, key, value)`.
+pub fn list_all_state(conn: &Connection, kek: &KeyEncryptionKey) -> RuntimeResult<Vec<(String, String, DataValue)>> {
+    list_state_rows(conn, kek, None)
+}
+
+/// Lists a recipe's decrypted state values as `(key, value)` ordered by key.
+pub fn list_state(conn: &Connection, kek: &KeyEncryptionKey, recipe_id: &str) -> RuntimeResult<Vec<(String, DataValue)>> {
+    Ok(list_state_rows(conn, kek, Some(recipe_id))?
+        .into_iter()
+        .map(|(_, key, value)| (key, value))
+        .collect())
+}
+
+fn list_state_rows(
+    conn: &Connection,
+    kek: &KeyEncryptionKey,
+    recipe_id: Option<&str>,
+) -> RuntimeResult<Vec<(String, String, DataValue)>> {
+    let rows: Vec<(String, String, String)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT recipe_id, key, value_json FROM state_kv
+                 WHERE ?1 IS NULL OR recipe_id = ?1 ORDER BY recipe_id, key",
+            )
+            .map_err(storage_err)?;
+        let rows = stmt
+            .query_map(params![recipe_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(storage_err)?;
+        rows.collect::<Result<_, _>>().map_err(storage_err)?
+    };
+
+    let mut cache = KeyCache::new(conn, kek);
+    rows.into_iter()
+        .map(|(recipe_id, key, stored)| {
+            let value = cache.open_json(&row_aad("state_kv", &recipe_id, &key), &stored)?;
+            Ok((recipe_id, key, value))
+        })
+        .collect()
+}
+
+/// Appends an encrypted execution log and returns its row id.
+pub fn append_execution_log(conn: &Connection, kek: &KeyEncryptionKey, log: &ExecutionLog) -> RuntimeResult<i64> {
+    let dek = active_data_key(conn, kek)?;
+    let sealed = seal_json(&dek, &row_aad("execution_logs", &log.recipe_id, &log.run_id), log)?;
+    conn.execute(
+        "INSERT INTO execution_logs (recipe_id, run_id, log_json, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![log.recipe_id, log.run_id, sealed, Utc::now().to_rfc3339()],
+    )
+    .map_err(storage_err)?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn list_sealed<T: DeserializeOwned>(
+    conn: &Connection,
+    kek: &KeyEncryptionKey,
+    table: &str,
+    column: &str,
+    recipe_id: Option<&str>,
+) -> RuntimeResult<Vec<T>> {
+    Ok(list_sealed_rows(conn, kek, table, column, recipe_id)?
+        .into_iter()
+        .map(|(_, _, value)| value)
+        .collect())
+}
+
+fn list_sealed_rows<T: DeserializeOwned>(
+    conn: &Connection,
+    kek: &KeyEncryptionKey,
+    table: &str,
+    column: &str,
+    recipe_id: Option<&str>,
+) -> RuntimeResult<Vec<(String, String, T)>> {
+    let rows: Vec<(String, String, String)> = {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT recipe_id, run_id, {} FROM {} WHERE ?1 IS NULL OR recipe_id = ?1 ORDER BY id",
+                column, table
+            ))
+            .map_err(storage_err)?;
+        let rows = stmt
+            .query_map(params![recipe_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(storage_err)?;
+        rows.collect::<Result<_, _>>().map_err(storage_err)?
+    };
+
+    let mut cache = KeyCache::new(conn, kek);
+    rows.into_iter()
+        .map(|(recipe_id, run_id, stored)| {
+            let value = cache.open_json(&row_aad(table, &recipe_id, &run_id), &stored)?;
+            Ok((recipe_id, run_id, value))
+        })
+        .collect()
+}
+
+/// Lists decrypted execution logs for a recipe in insertion order.
+pub fn list_execution_logs(
+    conn: &Connection,
+    kek: &KeyEncryptionKey,
+    recipe_id: &str,
+) -> RuntimeResult<Vec<ExecutionLog>> {
+    list_sealed(conn, kek, "execution_logs", "log_json", Some(recipe_id))
+}
+
+/// Lists decrypted execution logs across all recipes in insertion order.
+pub fn list_all_execution_logs(conn: &Connection, kek: &KeyEncryptionKey) -> RuntimeResult<Vec<ExecutionLog>> {
+    list_sealed(conn, kek, "execution_logs", "log_json", None)
+}
+
+/// Stores encrypted metadata for captured photo/audio/video media.
+pub fn put_media_metadata(
+    conn: &Connection,
+    kek: &KeyEncryptionKey,
+    recipe_id: &str,
+    run_id: &str,
+    media: &MediaRef,
+) -> RuntimeResult<i64> {
+    let dek = active_data_key(conn, kek)?;
+    let sealed = seal_json(&dek, &row_aad("captured_media", recipe_id, run_id), media)?;
+    conn.execute(
+        "INSERT INTO captured_media (recipe_id, run_id, meta_json, created_at) VALUES (?1, ?2, ?3, ?4)",
+        params![recipe_id, run_id, sealed, Utc::now().to_rfc3339()],
+    )
+    .map_err(storage_err)?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Lists decrypted captured-media metadata for a recipe.
+pub fn list_media_metadata(conn: &Connection, kek: &KeyEncryptionKey, recipe_id: &str) -> RuntimeResult<Vec<MediaRef>> {
+    list_sealed(conn, kek, "captured_media", "meta_json", Some(recipe_id))
+}
+
+/// Lists decrypted captured-media metadata across all recipes as `(recipe_id, run_id, media)`.
+pub fn list_all_media_metadata(
+    conn: &Connection,
+    kek: &KeyEncryptionKey,
+) -> RuntimeResult<Vec<(String, String, MediaRef)>> {
+    list_sealed_rows(conn, kek, "captured_media", "meta_json", None)
+}
+
+/// Returns true when `table` already holds a row for the run.
+pub(crate) fn run_recorded(conn: &Connection, table: &str, recipe_id: &str, run_id: &str) -> RuntimeResult<bool> {
+    conn.query_row(
+        &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE recipe_id = ?1 AND run_id = ?2)", table),
+        params![recipe_id, run_id],
+        |row| row.get(0),
+    )
+    .map_err(storage_err)
+}
+
+fn stale_row_filter(column: &str) -> String {
+    format!(
+        "(CASE WHEN json_valid({0}) THEN json_extract({0}, '$.kid') END) IS NOT ?1",
+        column
+    )
+}
+
+/// Re-encrypts up to `batch_size` rows that are not sealed with the active DEK.
+///
+/// Legacy plaintext rows are sealed as well. Hosts call this repeatedly from a
+/// background task after `keys::rotate_data_key` until it returns 0.
+pub fn reencrypt_stale_rows(conn: &Connection, kek: &KeyEncryptionKey, batch_size: usize) -> RuntimeResult<usize> {
+    let active = active_data_key(conn, kek)?;
+    let mut cache = KeyCache::new(conn, kek);
+    let mut processed = 0;
+
+    for (table, column, [first_col, second_col]) in ENCRYPTED_COLUMNS {
+        if processed >= batch_size {
+            break;
+        }
+        let rows: Vec<(i64, String, String, String)> = {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT rowid, {}, {}, {} FROM {} WHERE {} LIMIT ?2",
+                    first_col,
+                    second_col,
+                    column,
+                    table,
+                    stale_row_filter(column)
+                ))
+                .map_err(storage_err)?;
+            let rows = stmt
+                .query_map(params![active.id(), (batch_size - processed) as i64], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })
+                .map_err(storage_err)?;
+            rows.collect::<Result<_, _>>().map_err(storage_err)?
+        };
+
+        for (rowid, first, second, stored) in rows {
+            let aad = row_aad(table, &first, &second);
+            let plaintext = if Envelope::parse(&stored).is_ok() {
+                cache.open_text(&aad, &stored)?
+            } else {
+                stored.into_bytes()
+            };
+            let sealed = seal(&active, &aad, &plaintext)?.to_json()?;
+            conn.execute(
+                &format!("UPDATE {} SET {} = ?1 WHERE rowid = ?2", table, column),
+                params![sealed, rowid],
+            )
+            .map_err(storage_err)?;
+            processed += 1;
+        }
+    }
+    Ok(processed)
+}
+
+/// Lists DEK ids still referenced by at least one sealed row.
+pub fn referenced_data_key_ids(conn: &Connection) -> RuntimeResult<Vec<String>> {
+    let mut ids = Vec::new();
+    for (table, column, _) in ENCRYPTED_COLUMNS {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT DISTINCT json_extract({0}, '$.kid') FROM {1} WHERE json_valid({0}) AND json_extract({0}, '$.kid') IS NOT NULL",
+                column, table
+            ))
+            .map_err(storage_err)?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(storage_err)?;
+        for id in rows {
+            let id = id.map_err(storage_err)?;
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    Ok(ids)
+}
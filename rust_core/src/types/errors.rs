@@ -11,12 +11,22 @@ pub enum RuntimeError {
     SandboxViolation(String),
     #[error("schema validation failed: {0}")]
     SchemaValidation(String),
+    #[error("{file} violates schema at {pointer:?}: {reason}")]
+    SchemaViolation { file: String, pointer: String, reason: String },
     #[error("signature invalid")]
     SignatureInvalid,
+    #[error("untrusted publisher: {0}")]
+    UntrustedPublisher(String),
+    #[error("package file {file}: {reason}")]
+    Package { file: String, reason: String },
     #[error("connector error: {0}")]
     Connector(String),
     #[error("storage error: {0}")]
     Storage(String),
+    #[error("crypto error: {0}")]
+    Crypto(String),
+    #[error("audit chain broken at entry {seq}: {reason}")]
+    AuditChainBroken { seq: i64, reason: String },
     #[error("serialization error: {0}")]
     Serialization(String),
 }